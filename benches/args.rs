@@ -0,0 +1,43 @@
+//! The cost of collecting bind values, per `Arguments` implementation, against
+//! discarding them with `()`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sql_builder_test::{build_query, PackedArgs, Values};
+
+fn args(c: &mut Criterion) {
+    let (org, name, age) = (7_i64, "kim", Some(42_i32));
+    let mut group = c.benchmark_group("args");
+
+    group.bench_function("values", |b| {
+        b.iter(|| {
+            let (org, name, age) = black_box((org, name, age));
+            build_query!(
+                #![args(Values)]
+                "SELECT * FROM users WHERE org = " org " AND name = " name " AND age = " age
+            )
+        })
+    });
+    group.bench_function("packed", |b| {
+        b.iter(|| {
+            let (org, name, age) = black_box((org, name, age));
+            build_query!(
+                #![args(PackedArgs)]
+                "SELECT * FROM users WHERE org = " org " AND name = " name " AND age = " age
+            )
+        })
+    });
+    group.bench_function("discarded", |b| {
+        b.iter(|| {
+            let (org, name, age) = black_box((org, name, age));
+            build_query!(
+                #![args(())]
+                "SELECT * FROM users WHERE org = " org " AND name = " name " AND age = " age
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, args);
+criterion_main!(benches);
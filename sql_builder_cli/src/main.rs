@@ -0,0 +1,192 @@
+//! Inspect the `build_query!` invocations of a crate without compiling it.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use sql_builder_syntax::{format, scan, variants};
+
+mod schema;
+
+const USAGE: &str = "\
+usage: sql-builder <command> [options] [paths...]
+
+Scans the .rs files under the paths (default: src) for build_query! invocations.
+
+commands:
+    list                    print every variant of every invocation
+    check --schema <file>   check table and column names against a schema file
+    stats                   print variant and bind counts
+    fmt [--check]           format the invocations in place, or with --check
+                            list the files that would change
+";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next() {
+        Some(command) => command,
+        None => return usage_error("missing command"),
+    };
+
+    let mut schema_path = None;
+    let mut check_only = false;
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => match args.next() {
+                Some(path) => schema_path = Some(PathBuf::from(path)),
+                None => return usage_error("--schema needs a file"),
+            },
+            "--check" => check_only = true,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("src"));
+    }
+
+    if command == "fmt" {
+        return fmt(&paths, check_only);
+    }
+
+    let scan = scan::scan(&paths);
+    for error in &scan.errors {
+        eprintln!("{}: error: {}", error.location, error.message);
+    }
+
+    let ok = match command.as_str() {
+        "list" => {
+            list(&scan);
+            true
+        }
+        "stats" => {
+            stats(&scan);
+            true
+        }
+        "check" => {
+            let schema_path = match schema_path {
+                Some(path) => path,
+                None => return usage_error("check needs --schema <file>"),
+            };
+            let schema = match std::fs::read_to_string(&schema_path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| schema::Schema::parse(&source))
+            {
+                Ok(schema) => schema,
+                Err(err) => {
+                    eprintln!("{}: error: {}", schema_path.display(), err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            check(&scan, &schema)
+        }
+        "-h" | "--help" => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => return usage_error(&format!("unknown command `{}`", command)),
+    };
+
+    if ok && scan.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprint!("error: {}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn fmt(paths: &[PathBuf], check_only: bool) -> ExitCode {
+    let mut ok = true;
+    for file in scan::files(paths) {
+        let result = std::fs::read_to_string(&file)
+            .map_err(|err| err.to_string())
+            .and_then(|source| {
+                let formatted = format::format_source(&source).map_err(|err| err.to_string())?;
+                Ok((source, formatted))
+            });
+        let (source, formatted) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("{}: error: {}", file.display(), err);
+                ok = false;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check_only {
+            println!("{}", file.display());
+            ok = false;
+        } else if let Err(err) = std::fs::write(&file, formatted) {
+            eprintln!("{}: error: {}", file.display(), err);
+            ok = false;
+        }
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn list(scan: &scan::Scan) {
+    for invocation in &scan.invocations {
+        println!("{}", invocation.location());
+        for (index, variant) in invocation.variants.iter().enumerate() {
+            println!("  [{}] {}", index, variant.render(invocation.style).sql);
+        }
+        if invocation.truncated {
+            println!("  ... (truncated at {} variants)", variants::MAX_VARIANTS);
+        }
+    }
+}
+
+fn stats(scan: &scan::Scan) {
+    let mut total_variants = 0;
+    let mut max_variants = (0, None);
+    let mut max_binds = (0, None);
+
+    for invocation in &scan.invocations {
+        total_variants += invocation.variants.len();
+        if invocation.variants.len() > max_variants.0 {
+            max_variants = (invocation.variants.len(), Some(invocation));
+        }
+        for variant in &invocation.variants {
+            let binds = variant.render(invocation.style).binds;
+            if binds > max_binds.0 {
+                max_binds = (binds, Some(invocation));
+            }
+        }
+    }
+
+    println!("invocations:  {}", scan.invocations.len());
+    println!("variants:     {}", total_variants);
+    for (label, (count, invocation)) in [("max variants", max_variants), ("max binds", max_binds)] {
+        match invocation {
+            Some(invocation) => println!("{}: {} ({})", label, count, invocation.location()),
+            None => println!("{}: 0", label),
+        }
+    }
+}
+
+fn check(scan: &scan::Scan, schema: &schema::Schema) -> bool {
+    let mut ok = true;
+    for invocation in &scan.invocations {
+        for (index, variant) in invocation.variants.iter().enumerate() {
+            for problem in schema.check(&variant.render(invocation.style).sql) {
+                println!("{}: variant {}: {}", invocation.location(), index, problem);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
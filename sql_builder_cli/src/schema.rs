@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tables and their columns, read from a file of lines like
+///
+/// ```text
+/// # comment
+/// users: id, name, email
+/// ```
+#[derive(Debug, Default)]
+pub struct Schema {
+    tables: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Schema {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut schema = Schema::default();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (table, columns) = line
+                .split_once(':')
+                .ok_or_else(|| format!("line {}: expected `table: column, ...`", index + 1))?;
+            schema.tables.insert(
+                table.trim().to_lowercase(),
+                columns
+                    .split(',')
+                    .map(|column| column.trim().to_lowercase())
+                    .filter(|column| !column.is_empty())
+                    .collect(),
+            );
+        }
+        Ok(schema)
+    }
+
+    /// Check the table and qualified column names in `sql`.
+    ///
+    /// This is a token-level heuristic, not a SQL parser: a table is whatever
+    /// follows `FROM`, `JOIN`, `INTO` or `UPDATE`, and a column is checked only when
+    /// qualified by a known table or alias (`t.column`). Names defined by `WITH`
+    /// are accepted as tables.
+    pub fn check(&self, sql: &str) -> Vec<String> {
+        let tokens = tokenize(sql);
+        let mut problems = vec![];
+        let mut aliases: BTreeMap<String, String> = BTreeMap::new();
+        let mut ctes: BTreeSet<String> = BTreeSet::new();
+
+        for (index, token) in tokens.iter().enumerate() {
+            if token == "with" || (token == "," && !ctes.is_empty()) {
+                if let (Some(name), Some(as_)) = (tokens.get(index + 1), tokens.get(index + 2)) {
+                    if as_ == "as" && is_identifier(name) {
+                        ctes.insert(name.clone());
+                    }
+                }
+            }
+        }
+
+        for (index, token) in tokens.iter().enumerate() {
+            if !matches!(token.as_str(), "from" | "join" | "into" | "update") {
+                continue;
+            }
+            let table = match tokens.get(index + 1) {
+                Some(table) if is_identifier(table) && !is_keyword(table) => table,
+                _ => continue,
+            };
+            if ctes.contains(table) {
+                continue;
+            }
+            if !self.tables.contains_key(table) {
+                problems.push(format!("unknown table `{}`", table));
+                continue;
+            }
+            aliases.insert(table.clone(), table.clone());
+            let alias = match tokens.get(index + 2).map(String::as_str) {
+                Some("as") => tokens.get(index + 3),
+                _ => tokens.get(index + 2),
+            };
+            if let Some(alias) = alias {
+                if is_identifier(alias) && !is_keyword(alias) {
+                    aliases.insert(alias.clone(), table.clone());
+                }
+            }
+        }
+
+        for token in &tokens {
+            let (qualifier, column) = match token.split_once('.') {
+                Some(parts) => parts,
+                None => continue,
+            };
+            let table = match aliases.get(qualifier) {
+                Some(table) => table,
+                None => continue,
+            };
+            if column != "*" && !self.tables[table].contains(column) {
+                problems.push(format!("unknown column `{}` in table `{}`", column, table));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Lowercased words (with `.` kept inside qualified names), single punctuation
+/// characters, and nothing for string literals, quoted identifiers and comments.
+fn tokenize(sql: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut word = c.to_lowercase().to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_alphanumeric() || next == '_' || next == '.' || next == '*' {
+                        word.extend(next.to_lowercase());
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(word);
+            }
+            c if c.is_whitespace() => {}
+            c => tokens.push(c.to_string()),
+        }
+    }
+
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+fn is_keyword(token: &str) -> bool {
+    matches!(
+        token,
+        "select"
+            | "where"
+            | "set"
+            | "values"
+            | "on"
+            | "using"
+            | "join"
+            | "left"
+            | "right"
+            | "inner"
+            | "outer"
+            | "cross"
+            | "natural"
+            | "group"
+            | "order"
+            | "limit"
+            | "offset"
+            | "returning"
+            | "union"
+            | "default"
+            | "lateral"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::parse(
+            "# test schema\n\
+             users: id, name\n\
+             posts: id, user_id, title # trailing comment\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_known_names() {
+        let problems = schema().check(
+            "SELECT u.name, p.title FROM users u JOIN posts AS p ON p.user_id = u.id \
+             WHERE u.name = 'from nowhere' AND p.id IN ($1, $2)",
+        );
+        assert_eq!(problems, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_unknown_names() {
+        let problems = schema().check("SELECT users.email FROM users, lol WHERE TRUE");
+        assert_eq!(problems, vec!["unknown column `email` in table `users`"]);
+
+        let problems = schema().check("INSERT INTO comments (id) SELECT id FROM lol");
+        assert_eq!(
+            problems,
+            vec!["unknown table `comments`", "unknown table `lol`"]
+        );
+    }
+
+    #[test]
+    fn accepts_ctes_and_subqueries() {
+        let problems = schema().check(
+            "WITH recent AS (SELECT * FROM posts), mine AS (SELECT 1) \
+             SELECT * FROM recent JOIN mine ON TRUE WHERE id IN (SELECT id FROM users)",
+        );
+        assert_eq!(problems, Vec::<String>::new());
+    }
+
+    #[test]
+    fn rejects_malformed_schema() {
+        assert!(Schema::parse("users id, name").is_err());
+    }
+}
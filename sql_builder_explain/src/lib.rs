@@ -0,0 +1,677 @@
+//! Snapshot tests for the SQLite query plans of every `build_query!` variant.
+//!
+//! Performance regressions hide in rarely taken branches, so instead of planning
+//! the queries a test happens to build, this runs `EXPLAIN QUERY PLAN` on every
+//! variant the sources can produce and compares the plans with snapshot files
+//! committed next to the tests:
+//!
+//! ```no_run
+//! #[test]
+//! fn query_plans() {
+//!     sql_builder_explain::PlanSnapshots::new(include_str!("schema.sql"), "tests/plans")
+//!         .source("src")
+//!         .assert();
+//! }
+//! ```
+//!
+//! Run the test with `SQL_BUILDER_UPDATE_PLANS=1` to write or accept snapshots.
+//! The snapshot directory belongs to the check: snapshots of source files that no
+//! longer have invocations fail it, and updating deletes them.
+//! Repeated parts (IN lists and batch rows) are planned with one item. What is
+//! spliced in is only known at runtime: predicates in `where` and `or` groups are
+//! planned as `1`, subqueries as `SELECT NULL` and whole clauses as nothing. A
+//! variant that still can't be planned is recorded as not planned instead.
+//! Invocations with a `#![dialect(...)]` other than `Sqlite` are skipped.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use sql_builder_syntax::{scan, variants::PlaceholderStyle};
+
+/// The environment variable that makes [`PlanSnapshots`] write its snapshots
+/// instead of comparing against them.
+pub const UPDATE_ENV: &str = "SQL_BUILDER_UPDATE_PLANS";
+
+/// The query plans of the `build_query!` invocations under some source paths,
+/// checked against one snapshot file per source file.
+pub struct PlanSnapshots {
+    schema: String,
+    dir: PathBuf,
+    sources: Vec<PathBuf>,
+    update: bool,
+}
+
+impl PlanSnapshots {
+    /// Plans are made against a fresh in-memory database initialized with the
+    /// `schema` statements, and snapshots live in `dir`.
+    pub fn new(schema: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        PlanSnapshots {
+            schema: schema.into(),
+            dir: dir.into(),
+            sources: vec![],
+            update: std::env::var_os(UPDATE_ENV).is_some_and(|value| value != "0"),
+        }
+    }
+
+    /// Add a source file or directory to scan for invocations.
+    pub fn source(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(path.into());
+        self
+    }
+
+    /// Write the snapshots instead of checking them. Defaults to whether
+    /// [`UPDATE_ENV`] is set.
+    pub fn update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Panic with the report unless every plan matches its snapshot.
+    pub fn assert(&self) {
+        if let Err(report) = self.check() {
+            panic!("{}", report);
+        }
+    }
+
+    pub fn check(&self) -> Result<(), Report> {
+        let mut problems = vec![];
+
+        let conn = rusqlite::Connection::open_in_memory()
+            .and_then(|conn| conn.execute_batch(&self.schema).map(|_| conn));
+        let conn = match conn {
+            Ok(conn) => conn,
+            Err(err) => {
+                problems.push(Problem::Schema(err.to_string()));
+                return Err(Report { problems });
+            }
+        };
+
+        let scan = scan::scan(&self.sources);
+        for error in scan.errors {
+            problems.push(Problem::Scan {
+                location: error.location,
+                message: error.message,
+            });
+        }
+
+        let mut files: BTreeMap<&Path, Vec<Entry>> = BTreeMap::new();
+        let mut nth: BTreeMap<(&Path, Option<&str>), usize> = BTreeMap::new();
+        for invocation in &scan.invocations {
+            let count = nth
+                .entry((&invocation.file, invocation.function.as_deref()))
+                .or_default();
+            // Invocations for other databases keep their place in the numbering.
+            if !matches!(invocation.dialect.as_deref(), None | Some("Sqlite")) {
+                *count += 1;
+                continue;
+            }
+            let entries = files.entry(&invocation.file).or_default();
+            for (index, variant) in invocation.variants.iter().enumerate() {
+                let key = format!(
+                    "{} #{} variant {}",
+                    invocation.function.as_deref().unwrap_or("<module>"),
+                    count,
+                    index
+                );
+                let sql = variant.render_once(PlaceholderStyle::QuestionFromOne).sql;
+                match explain(&conn, &sql) {
+                    Ok(plan) => entries.push(Entry { key, sql, plan }),
+                    Err(err) if variant.has_splices() => entries.push(Entry {
+                        key,
+                        sql,
+                        plan: vec![format!("not planned: {}", err)],
+                    }),
+                    Err(err) => problems.push(Problem::Explain {
+                        location: invocation.location(),
+                        key,
+                        sql,
+                        message: err.to_string(),
+                    }),
+                }
+            }
+            *count += 1;
+        }
+
+        let mut snapshots = BTreeSet::new();
+        for (file, entries) in files {
+            let snapshot = self.snapshot_path(file);
+            snapshots.insert(snapshot.clone());
+            if self.update {
+                let written = std::fs::create_dir_all(&self.dir)
+                    .and_then(|_| std::fs::write(&snapshot, to_snapshot(&entries)));
+                if let Err(err) = written {
+                    problems.push(Problem::Write {
+                        snapshot,
+                        message: err.to_string(),
+                    });
+                }
+                continue;
+            }
+            match std::fs::read_to_string(&snapshot) {
+                Ok(old) => compare(&snapshot, &parse_snapshot(&old), &entries, &mut problems),
+                Err(_) => problems.push(Problem::Missing { snapshot }),
+            }
+        }
+
+        self.stale(&snapshots, &mut problems);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Report { problems })
+        }
+    }
+
+    /// Report the `.plan` files in the snapshot directory that aren't among
+    /// `snapshots`, or delete them when updating.
+    fn stale(&self, snapshots: &BTreeSet<PathBuf>, problems: &mut Vec<Problem>) {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(_) => return,
+        };
+        let mut stale: Vec<PathBuf> = dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "plan"))
+            .filter(|path| !snapshots.contains(path))
+            .collect();
+        stale.sort();
+
+        for snapshot in stale {
+            if !self.update {
+                problems.push(Problem::Stale { snapshot });
+            } else if let Err(err) = std::fs::remove_file(&snapshot) {
+                problems.push(Problem::Write {
+                    snapshot,
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+
+    /// `<dir>/<file>.plan`, with path separators in the source file name
+    /// replaced by `_`.
+    fn snapshot_path(&self, file: &Path) -> PathBuf {
+        let name: String = file
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+            .collect();
+        self.dir.join(format!("{}.plan", name))
+    }
+}
+
+/// The plan of one variant.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    key: String,
+    sql: String,
+    /// Plan details, indented two spaces per level.
+    plan: Vec<String>,
+}
+
+impl Entry {
+    /// The tables the plan reads in full.
+    fn scans(&self) -> BTreeSet<&str> {
+        self.plan
+            .iter()
+            .filter_map(|line| line.trim_start().strip_prefix("SCAN "))
+            .map(|rest| rest.strip_prefix("TABLE ").unwrap_or(rest))
+            .filter_map(|rest| rest.split_whitespace().next())
+            .filter(|table| !table.starts_with('(') && *table != "CONSTANT")
+            .collect()
+    }
+}
+
+fn explain(conn: &rusqlite::Connection, sql: &str) -> rusqlite::Result<Vec<String>> {
+    let mut statement = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+    // Plans don't depend on the values, so every parameter is NULL.
+    let params = vec![rusqlite::types::Null; statement.parameter_count()];
+    let rows = statement.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut depths = BTreeMap::new();
+    let mut plan = vec![];
+    for row in rows {
+        let (id, parent, detail) = row?;
+        let depth = depths.get(&parent).map_or(0, |depth| depth + 1);
+        depths.insert(id, depth);
+        plan.push(format!("{}{}", "  ".repeat(depth), detail));
+    }
+    Ok(plan)
+}
+
+/// Entries as `-- key` header lines, each followed by the SQL on one line and
+/// the plan.
+fn to_snapshot(entries: &[Entry]) -> String {
+    let mut snapshot = String::new();
+    for entry in entries {
+        snapshot.push_str(&format!(
+            "-- {}\n{}\n",
+            entry.key,
+            entry.sql.replace('\n', " ")
+        ));
+        for line in &entry.plan {
+            snapshot.push_str(line);
+            snapshot.push('\n');
+        }
+        snapshot.push('\n');
+    }
+    snapshot
+}
+
+fn parse_snapshot(snapshot: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    for line in snapshot.lines() {
+        if let Some(key) = line.strip_prefix("-- ") {
+            entries.push(Entry {
+                key: key.to_owned(),
+                sql: String::new(),
+                plan: vec![],
+            });
+            continue;
+        }
+        match entries.last_mut() {
+            Some(entry) if entry.sql.is_empty() => entry.sql = line.to_owned(),
+            Some(entry) if !line.is_empty() => entry.plan.push(line.to_owned()),
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn compare(snapshot: &Path, old: &[Entry], new: &[Entry], problems: &mut Vec<Problem>) {
+    let old: BTreeMap<&str, &Entry> = old.iter().map(|entry| (&*entry.key, entry)).collect();
+    let mut seen = BTreeSet::new();
+
+    for entry in new {
+        seen.insert(&*entry.key);
+        let sql = entry.sql.replace('\n', " ");
+        let old_entry = old.get(&*entry.key);
+        let changed = match old_entry {
+            Some(old_entry) => old_entry.sql != sql || old_entry.plan != entry.plan,
+            None => true,
+        };
+        if !changed {
+            continue;
+        }
+
+        let old_scans = old_entry
+            .map(|old_entry| old_entry.scans())
+            .unwrap_or_default();
+        for table in entry.scans().difference(&old_scans) {
+            problems.push(Problem::NewScan {
+                snapshot: snapshot.to_owned(),
+                key: entry.key.clone(),
+                table: table.to_string(),
+            });
+        }
+        problems.push(Problem::Changed {
+            snapshot: snapshot.to_owned(),
+            key: entry.key.clone(),
+            old: old_entry.map(|old_entry| render_entry(&old_entry.sql, &old_entry.plan)),
+            new: render_entry(&sql, &entry.plan),
+        });
+    }
+
+    for key in old.keys().filter(|key| !seen.contains(*key)) {
+        problems.push(Problem::Removed {
+            snapshot: snapshot.to_owned(),
+            key: key.to_string(),
+        });
+    }
+}
+
+fn render_entry(sql: &str, plan: &[String]) -> Vec<String> {
+    std::iter::once(sql.to_owned())
+        .chain(plan.iter().cloned())
+        .collect()
+}
+
+/// Why a plan check failed.
+#[derive(Debug)]
+pub enum Problem {
+    /// The schema statements failed.
+    Schema(String),
+    /// A source file couldn't be read or an invocation couldn't be parsed.
+    Scan {
+        location: String,
+        message: String,
+    },
+    /// SQLite couldn't plan a variant.
+    Explain {
+        location: String,
+        key: String,
+        sql: String,
+        message: String,
+    },
+    Missing {
+        snapshot: PathBuf,
+    },
+    Write {
+        snapshot: PathBuf,
+        message: String,
+    },
+    /// A variant's SQL or plan differs from its snapshot, or it has none.
+    Changed {
+        snapshot: PathBuf,
+        key: String,
+        /// The SQL and plan lines in the snapshot.
+        old: Option<Vec<String>>,
+        new: Vec<String>,
+    },
+    /// A variant reads a table in full that its snapshot didn't.
+    NewScan {
+        snapshot: PathBuf,
+        key: String,
+        table: String,
+    },
+    /// A snapshot has a variant the sources no longer produce.
+    Removed {
+        snapshot: PathBuf,
+        key: String,
+    },
+    /// A snapshot file has no source file with invocations.
+    Stale {
+        snapshot: PathBuf,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Schema(message) => write!(f, "schema: {}", message),
+            Problem::Scan { location, message } => write!(f, "{}: {}", location, message),
+            Problem::Explain {
+                location,
+                key,
+                sql,
+                message,
+            } => write!(f, "{}: {}: {}\n    {}", location, key, message, sql),
+            Problem::Missing { snapshot } => write!(f, "{}: no snapshot", snapshot.display()),
+            Problem::Write { snapshot, message } => {
+                write!(f, "{}: {}", snapshot.display(), message)
+            }
+            Problem::Changed {
+                snapshot,
+                key,
+                old,
+                new,
+            } => {
+                match old {
+                    Some(_) => write!(f, "{}: {}: plan changed", snapshot.display(), key)?,
+                    None => write!(f, "{}: {}: new variant", snapshot.display(), key)?,
+                }
+                for line in old.iter().flatten() {
+                    write!(f, "\n    - {}", line)?;
+                }
+                for line in new {
+                    write!(f, "\n    + {}", line)?;
+                }
+                Ok(())
+            }
+            Problem::NewScan {
+                snapshot,
+                key,
+                table,
+            } => write!(
+                f,
+                "{}: {}: new full-table scan of `{}`",
+                snapshot.display(),
+                key,
+                table
+            ),
+            Problem::Removed { snapshot, key } => {
+                write!(f, "{}: {}: variant removed", snapshot.display(), key)
+            }
+            Problem::Stale { snapshot } => {
+                write!(f, "{}: no invocations left", snapshot.display())
+            }
+        }
+    }
+}
+
+/// Everything that failed a [`PlanSnapshots::check`].
+#[derive(Debug)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "query plans don't match their snapshots:")?;
+        for problem in &self.problems {
+            writeln!(f, "{}", problem)?;
+        }
+        write!(f, "rerun with {}=1 to accept the new plans", UPDATE_ENV)
+    }
+}
+
+impl std::error::Error for Report {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "
+        CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, name TEXT);
+        CREATE INDEX users_email ON users (email);
+    ";
+
+    #[test]
+    fn snapshots_plans() {
+        let dir = std::env::temp_dir().join(format!("sql_builder_explain_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("repo.rs");
+        std::fs::write(
+            &source,
+            r#"
+fn find(email: Option<&str>) -> Query {
+    build_query!(
+        #![dialect(Sqlite)]
+        "SELECT id FROM users WHERE "
+        if let Some(email) = email { "email = " email } else { "id IN " in ids }
+    )
+}
+"#,
+        )
+        .unwrap();
+        let snapshots = |schema: &str| {
+            PlanSnapshots::new(schema, dir.join("plans"))
+                .source(&source)
+                .update(false)
+        };
+
+        let report = snapshots(SCHEMA).check().unwrap_err();
+        assert!(
+            matches!(report.problems[..], [Problem::Missing { .. }]),
+            "{}",
+            report
+        );
+
+        snapshots(SCHEMA).update(true).check().unwrap();
+        snapshots(SCHEMA).check().unwrap();
+        let written = std::fs::read_dir(dir.join("plans")).unwrap().count();
+        assert_eq!(written, 1);
+
+        let without_index = SCHEMA.replace("CREATE INDEX users_email ON users (email);", "");
+        let report = snapshots(&without_index).check().unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let messages: Vec<String> = report
+            .problems
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        assert_eq!(messages.len(), 2, "{:#?}", messages);
+        assert!(messages[0].ends_with("find #0 variant 0: new full-table scan of `users`"));
+        assert!(messages[1].contains("find #0 variant 0: plan changed"));
+        assert!(messages[1].contains("- SEARCH users USING COVERING INDEX users_email (email=?)"));
+        assert!(messages[1].contains("+ SCAN users"));
+    }
+
+    #[test]
+    fn skips_other_dialects() {
+        let dir = std::env::temp_dir().join(format!(
+            "sql_builder_explain_dialects_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("repo.rs");
+        std::fs::write(
+            &source,
+            r#"
+fn find(name: &str) -> Query {
+    let pg = build_query!(
+        #![dialect(Postgres)]
+        "SELECT id FROM users WHERE name ILIKE " name
+    );
+    build_query!("SELECT id FROM users WHERE name = " name)
+}
+"#,
+        )
+        .unwrap();
+
+        PlanSnapshots::new(SCHEMA, dir.join("plans"))
+            .source(&source)
+            .update(true)
+            .check()
+            .unwrap();
+        let snapshot = std::fs::read_dir(dir.join("plans"))
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!snapshot.contains("ILIKE"), "{}", snapshot);
+        assert!(snapshot.contains("-- find #1 variant 0"), "{}", snapshot);
+    }
+
+    #[test]
+    fn reports_stale_snapshots() {
+        let dir =
+            std::env::temp_dir().join(format!("sql_builder_explain_stale_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let kept = dir.join("kept.rs");
+        let emptied = dir.join("emptied.rs");
+        let source = r#"
+fn find(name: &str) -> Query {
+    build_query!("SELECT id FROM users WHERE name = " name)
+}
+"#;
+        std::fs::write(&kept, source).unwrap();
+        std::fs::write(&emptied, source).unwrap();
+        let snapshots = || {
+            PlanSnapshots::new(SCHEMA, dir.join("plans"))
+                .source(&kept)
+                .source(&emptied)
+                .update(false)
+        };
+
+        snapshots().update(true).check().unwrap();
+        assert_eq!(std::fs::read_dir(dir.join("plans")).unwrap().count(), 2);
+
+        std::fs::write(&emptied, "fn find() {}\n").unwrap();
+        let report = snapshots().check().unwrap_err();
+        let stale = snapshots().snapshot_path(&emptied);
+        assert!(
+            matches!(&report.problems[..], [Problem::Stale { snapshot }] if *snapshot == stale),
+            "{}",
+            report
+        );
+
+        snapshots().update(true).check().unwrap();
+        snapshots().check().unwrap();
+        let left: Vec<PathBuf> = std::fs::read_dir(dir.join("plans"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left, vec![snapshots().snapshot_path(&kept)]);
+    }
+
+    #[test]
+    fn plans_spliced_filters() {
+        let dir = std::env::temp_dir().join(format!(
+            "sql_builder_explain_splices_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("repo.rs");
+        std::fs::write(
+            &source,
+            r#"
+fn find(filter: &UserFilter, page: &Keyset<(i64,)>) -> Query {
+    build_query!(
+        #![dialect(Sqlite)]
+        "SELECT id FROM users"
+        where { "email = " email ..filter.predicates() }
+        ..page.order_by()
+    )
+}
+
+fn invited(by: Query) -> Query {
+    build_query!("SELECT id FROM users WHERE id IN (" ..by ")" ..by)
+}
+
+fn rename(changes: &UserChanges, id: i64) -> Query {
+    build_query!("UPDATE users" set { ..changes.assignments() } " WHERE id = " id)
+}
+"#,
+        )
+        .unwrap();
+
+        PlanSnapshots::new(SCHEMA, dir.join("plans"))
+            .source(&source)
+            .update(true)
+            .check()
+            .unwrap();
+        let snapshot = std::fs::read_dir(dir.join("plans"))
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(
+            snapshot.contains("SELECT id FROM users WHERE email = ?1 AND 1\n"),
+            "{}",
+            snapshot
+        );
+        assert!(
+            snapshot.contains("SELECT id FROM users WHERE id IN (SELECT NULL)\n"),
+            "{}",
+            snapshot
+        );
+        assert!(
+            snapshot
+                .contains("-- rename #0 variant 0\nUPDATE users SET  WHERE id = ?1\nnot planned: "),
+            "{}",
+            snapshot
+        );
+    }
+
+    #[test]
+    fn parses_snapshots() {
+        let entries = vec![
+            Entry {
+                key: "find #0 variant 0".to_owned(),
+                sql: "SELECT 1".to_owned(),
+                plan: vec!["SCAN CONSTANT ROW".to_owned()],
+            },
+            Entry {
+                key: "<module> #0 variant 0".to_owned(),
+                sql: "SELECT * FROM a JOIN b".to_owned(),
+                plan: vec!["SCAN a".to_owned(), "  SCAN TABLE b".to_owned()],
+            },
+        ];
+        let parsed = parse_snapshot(&to_snapshot(&entries));
+        assert_eq!(parsed, entries);
+        assert!(parsed[0].scans().is_empty());
+        assert_eq!(parsed[1].scans(), BTreeSet::from(["a", "b"]));
+    }
+}
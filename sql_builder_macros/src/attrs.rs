@@ -0,0 +1,113 @@
+//! `#[sql(...)]` attributes of the derives.
+
+/// One `#[sql(...)]` entry: a flag like `skip`, or `key = "value"`.
+pub struct Entry {
+    pub path: syn::Path,
+    pub value: Option<syn::LitStr>,
+}
+
+impl Entry {
+    pub fn is(&self, name: &str) -> bool {
+        self.path.is_ident(name)
+    }
+
+    pub fn flag(&self) -> syn::Result<bool> {
+        match &self.value {
+            None => Ok(true),
+            Some(value) => Err(syn::Error::new_spanned(
+                value,
+                format!("`{}` takes no value", self.name()),
+            )),
+        }
+    }
+
+    pub fn value(&self) -> syn::Result<syn::LitStr> {
+        self.value.clone().ok_or_else(|| {
+            syn::Error::new_spanned(&self.path, format!("expected `{} = \"...\"`", self.name()))
+        })
+    }
+
+    pub fn unknown(&self) -> syn::Error {
+        syn::Error::new_spanned(&self.path, "unknown sql attribute")
+    }
+
+    fn name(&self) -> String {
+        quote::ToTokens::to_token_stream(&self.path).to_string()
+    }
+}
+
+/// The entries of all `#[sql(...)]` attributes in `attrs`.
+pub fn entries(attrs: &[syn::Attribute]) -> syn::Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for attr in attrs {
+        if !attr.path.is_ident("sql") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[sql(...)]`")),
+        };
+        for nested in list.nested {
+            entries.push(match nested {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => Entry { path, value: None },
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(value),
+                    ..
+                })) => Entry {
+                    path,
+                    value: Some(value),
+                },
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "expected `name` or `name = \"...\"`",
+                    ))
+                }
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// The named fields of a derive input struct.
+pub fn named_fields<'a>(
+    input: &'a syn::DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a syn::punctuated::Punctuated<syn::Field, syn::Token![,]>> {
+    match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => Ok(&fields.named),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!(
+                "{} can only be derived for structs with named fields",
+                derive
+            ),
+        )),
+    }
+}
+
+/// The column of a field: its `rename`, or its name without any `r#`.
+pub fn column(field: &syn::Field, rename: Option<syn::LitStr>) -> String {
+    match rename {
+        Some(rename) => rename.value(),
+        None => {
+            let ident = field.ident.as_ref().unwrap().to_string();
+            ident.trim_start_matches("r#").to_owned()
+        }
+    }
+}
+
+/// `#[sql(table = "...")]` on the struct.
+pub fn table(input: &syn::DeriveInput, entries: &[Entry]) -> syn::Result<String> {
+    match entries.iter().find(|entry| entry.is("table")) {
+        Some(entry) => Ok(entry.value()?.value()),
+        None => Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[sql(table = \"...\")]`",
+        )),
+    }
+}
@@ -3,82 +3,334 @@ use quote::quote;
 use std::iter::IntoIterator;
 
 use crate::blocks;
+use crate::manifest;
 use crate::parse;
+use crate::variants;
 
 struct GenData {
     builder_path: syn::Path,
+    sink_path: syn::Path,
+    dialect_path: syn::Path,
+    arguments_path: syn::Path,
+    vec_path: syn::Path,
+    origin_path: syn::Path,
+    location_path: syn::Path,
+    branch_step_path: syn::Path,
+    fragment_kind_path: syn::Path,
+    fingerprint_path: syn::Path,
+    group_kind_path: syn::Path,
     builder_ident: proc_macro2::Ident,
+    sink: syn::Expr,
+    dialect: syn::Type,
+    args: syn::Type,
+    try_build: bool,
+    fingerprint_name: Option<syn::LitStr>,
+    call_site: Option<manifest::CallSite>,
 }
 
 impl Default for GenData {
     fn default() -> Self {
         GenData {
             builder_path: syn::parse_str("sql_builder_test::Builder").unwrap(),
+            sink_path: syn::parse_str("sql_builder_test::Sink").unwrap(),
+            dialect_path: syn::parse_str("sql_builder_test::Dialect").unwrap(),
+            arguments_path: syn::parse_str("sql_builder_test::Arguments").unwrap(),
+            vec_path: syn::parse_str("sql_builder_test::__private::Vec").unwrap(),
+            origin_path: syn::parse_str("sql_builder_test::Origin").unwrap(),
+            location_path: syn::parse_str("sql_builder_test::Location").unwrap(),
+            branch_step_path: syn::parse_str("sql_builder_test::BranchStep").unwrap(),
+            fragment_kind_path: syn::parse_str("sql_builder_test::FragmentKind").unwrap(),
+            fingerprint_path: syn::parse_str("sql_builder_test::Fingerprint").unwrap(),
+            group_kind_path: syn::parse_str("sql_builder_test::GroupKind").unwrap(),
             builder_ident: quote::format_ident!("builder"),
+            sink: syn::parse_str("sql_builder_test::DefaultSink::default()").unwrap(),
+            dialect: syn::parse_str("sql_builder_test::Generic").unwrap(),
+            args: syn::parse_str("sql_builder_test::DefaultArgs").unwrap(),
+            try_build: false,
+            fingerprint_name: None,
+            call_site: None,
+        }
+    }
+}
+
+impl GenData {
+    fn from_attrs(
+        attrs: &[syn::Attribute],
+        call_site: Option<manifest::CallSite>,
+    ) -> syn::Result<Self> {
+        let mut gen_data = Self {
+            call_site,
+            ..Self::default()
+        };
+        for attr in attrs {
+            if attr.path.is_ident("sink") {
+                gen_data.sink = attr.parse_args()?;
+            } else if attr.path.is_ident("dialect") {
+                gen_data.dialect = attr.parse_args()?;
+            } else if attr.path.is_ident("args") {
+                gen_data.args = attr.parse_args()?;
+            } else if attr.path.is_ident("try_build") {
+                gen_data.try_build = true;
+            } else if attr.path.is_ident("fingerprint") {
+                gen_data.fingerprint_name = Some(attr.parse_args()?);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    &attr.path,
+                    "unknown build_query attribute",
+                ));
+            }
+        }
+        Ok(gen_data)
+    }
+
+    /// The `Location` of the invocation, where the manifest puts it too.
+    fn call_site_location(&self) -> TokenStream {
+        let location_path = &self.location_path;
+        match &self.call_site {
+            Some(call_site) => {
+                let file = &call_site.file;
+                let line = call_site.line as u32;
+                let column = call_site.column as u32;
+                quote! {
+                    #location_path {
+                        file: #file,
+                        line: #line,
+                        column: #column,
+                    }
+                }
+            }
+            None => quote! {
+                #location_path {
+                    file: ::core::file!(),
+                    line: ::core::line!(),
+                    column: ::core::column!(),
+                }
+            },
         }
     }
 }
 
 fn get_sql_fmt_fn_ident(id: &blocks::NodeId) -> proc_macro2::Ident {
     if let Some(parent) = &id.parent {
-        quote::format_ident!("{}_{}", get_sql_fmt_fn_ident(&parent), id.local_index)
+        quote::format_ident!("{}_{}", get_sql_fmt_fn_ident(parent), id.local_index)
     } else {
         quote::format_ident!("sql_fmt_{}", id.local_index)
     }
 }
 
+/// Where the fragment functions being generated are in the source, for fragment origins.
+#[derive(Default)]
+struct SourceContext {
+    /// Running source-order indices of literals and binds.
+    literals: usize,
+    binds: usize,
+    /// Keywords and lines of the branches entered to get here.
+    branches: Vec<(String, u32)>,
+}
+
+impl SourceContext {
+    fn enter(&mut self, keywords: &TokenStream) {
+        let line = keywords
+            .clone()
+            .into_iter()
+            .next()
+            .map(|token| token.span().start().line as u32)
+            .unwrap_or(0);
+        self.branches.push((keywords.to_string(), line));
+    }
+
+    fn leave(&mut self) {
+        self.branches.pop();
+    }
+}
+
+fn get_node_path(id: &blocks::NodeId) -> Vec<u16> {
+    let mut path = match &id.parent {
+        Some(parent) => get_node_path(parent),
+        None => vec![],
+    };
+    path.push(id.local_index);
+    path
+}
+
+fn gen_origin(
+    span: proc_macro2::Span,
+    path: &[u16],
+    kind: TokenStream,
+    context: &SourceContext,
+    gen_data: &GenData,
+) -> TokenStream {
+    let origin_path = &gen_data.origin_path;
+    let location_path = &gen_data.location_path;
+    let branch_step_path = &gen_data.branch_step_path;
+    let call_site = gen_data.call_site_location();
+    let start = span.start();
+    let line = start.line as u32;
+    let column = start.column as u32 + 1;
+    let branches = context.branches.iter().map(|(keyword, line)| {
+        quote! {
+            #branch_step_path { keyword: #keyword, line: #line }
+        }
+    });
+    quote! {
+        &#origin_path {
+            location: #location_path {
+                file: ::core::file!(),
+                line: #line,
+                column: #column,
+            },
+            call_site: #call_site,
+            path: &[#(#path),*],
+            branches: &[#(#branches),*],
+            kind: #kind,
+        }
+    }
+}
+
+/// 64-bit FNV-1a, the same hash as `Fingerprint::hash` at runtime.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The SQL of a block for fingerprinting: `?` for binds, whitespace collapsed and
+/// ASCII lowercased, so reformatting the source doesn't change it.
+fn sql_shape(pushes: &[blocks::Push]) -> String {
+    let mut sql = String::new();
+    for push in pushes {
+        match push {
+            blocks::Push::Lit(lit_str) => sql.push_str(&lit_str.value()),
+            blocks::Push::Bind(_) => sql.push_str(" ? "),
+            blocks::Push::Empty => {}
+        }
+    }
+    sql.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_lowercase()
+}
+
+fn gen_fold_fingerprint(builder_ident: &proc_macro2::Ident, shape: &str) -> TokenStream {
+    let hash = proc_macro2::Literal::u64_suffixed(fnv1a(shape.as_bytes()));
+    quote! {
+        #builder_ident.fold_fingerprint(#hash);
+    }
+}
+
+/// `fold` is false for batch rows, which are fingerprinted once however many
+/// rows there are.
 fn gen_sql_fmt_fn(
     pushes: &[blocks::Push],
     node_id: &blocks::NodeId,
+    fold: bool,
+    context: &mut SourceContext,
     gen_data: &GenData,
 ) -> TokenStream {
+    use syn::spanned::Spanned;
+
     let builder_ident = quote::format_ident!("b");
-    let stmts: Vec<_> = pushes
-        .into_iter()
-        .map(|push| match push {
+    let fragment_kind_path = &gen_data.fragment_kind_path;
+    let path = get_node_path(node_id);
+    let mut stmts = vec![];
+    if fold {
+        let shape = sql_shape(pushes);
+        stmts.push(gen_fold_fingerprint(&builder_ident, &shape));
+        // The shape as well as the position, so that invocations with different
+        // SQL in the same place don't share variant ids.
+        let variant_hash =
+            proc_macro2::Literal::u64_suffixed(fnv1a(format!("{:?} {}", path, shape).as_bytes()));
+        stmts.push(quote! {
+            #builder_ident.fold_variant(#variant_hash);
+        });
+    }
+
+    for push in pushes {
+        stmts.push(match push {
             blocks::Push::Lit(lit_str) => {
+                let index = context.literals;
+                context.literals += 1;
+                let origin = gen_origin(
+                    lit_str.span(),
+                    &path,
+                    quote! { #fragment_kind_path::Literal(#index) },
+                    context,
+                    gen_data,
+                );
                 quote! {
-                    #builder_ident.push_sql(#lit_str);
+                    #builder_ident.push_sql_at(#lit_str, #origin);
                 }
             }
-            blocks::Push::Bind(_) => {
+            blocks::Push::Bind(expr) => {
+                let index = context.binds;
+                context.binds += 1;
+                let origin = gen_origin(
+                    expr.span(),
+                    &path,
+                    quote! { #fragment_kind_path::Bind(#index) },
+                    context,
+                    gen_data,
+                );
                 quote! {
-                    #builder_ident.push_sql_arg();
+                    #builder_ident.push_sql_arg_at(#origin);
                 }
             }
             blocks::Push::Empty => {
                 quote! {}
             }
-        })
-        .collect();
+        });
+    }
 
     let builder_path = &gen_data.builder_path;
+    let sink_path = &gen_data.sink_path;
+    let dialect_path = &gen_data.dialect_path;
+    let arguments_path = &gen_data.arguments_path;
     let fn_ident = get_sql_fmt_fn_ident(node_id);
 
     quote! {
-        fn #fn_ident(#builder_ident: &mut #builder_path) {
+        fn #fn_ident<S: #sink_path, D: #dialect_path, A: #arguments_path>(
+            #builder_ident: &mut #builder_path<S, D, A>,
+        ) {
             #(#stmts)*
         }
     }
 }
 
-fn gen_sql_fmt_fns(blocks: &[blocks::Block], gen_data: &GenData) -> TokenStream {
-    let output: Vec<_> = blocks
-        .into_iter()
-        .map(|block| match &block.op {
-            blocks::Op::Push(pushes) => gen_sql_fmt_fn(pushes, &block.id, gen_data),
-            blocks::Op::Branch(branches) => {
-                let output: Vec<_> = branches
-                    .into_iter()
-                    .map(|branch| gen_sql_fmt_fns(&branch.then, gen_data))
-                    .collect();
+fn gen_sql_fmt_fns(
+    blocks: &[blocks::Block],
+    fold: bool,
+    context: &mut SourceContext,
+    gen_data: &GenData,
+) -> TokenStream {
+    let mut output = vec![];
 
-                quote! {
-                    #(#output)*
+    for block in blocks {
+        match &block.op {
+            blocks::Op::Push(pushes) => {
+                output.push(gen_sql_fmt_fn(pushes, &block.id, fold, context, gen_data))
+            }
+            blocks::Op::Branch(branches) => {
+                for branch in branches {
+                    context.enter(&branch.keywords);
+                    output.push(gen_sql_fmt_fns(&branch.then, fold, context, gen_data));
+                    context.leave();
                 }
             }
-        })
-        .collect();
+            blocks::Op::Batch(batch) => {
+                let for_token = &batch.for_token;
+                context.enter(&quote! { #for_token });
+                output.push(gen_sql_fmt_fns(&batch.row, false, context, gen_data));
+                context.leave();
+            }
+            blocks::Op::InList(_) => context.binds += 1,
+            blocks::Op::Splice(_) | blocks::Op::OrderBy(_) => {}
+            blocks::Op::Group(group) => {
+                for item in &group.items {
+                    output.push(gen_sql_fmt_fns(item, fold, context, gen_data));
+                }
+            }
+        }
+    }
 
     quote! {
         #(#output)*
@@ -86,7 +338,7 @@ fn gen_sql_fmt_fns(blocks: &[blocks::Block], gen_data: &GenData) -> TokenStream
 }
 
 fn gen_branch(branch: blocks::Branch, gen_data: &GenData) -> TokenStream {
-    let then = gen_blocks(branch.then, &gen_data);
+    let then = gen_blocks(branch.then, gen_data);
     let keywords = branch.keywords;
     if let Some(cond) = branch.cond {
         quote! {
@@ -141,13 +393,376 @@ fn gen_block(block: blocks::Block, gen_data: &GenData) -> TokenStream {
             }
         }
         blocks::Op::Branch(branches) => gen_branches(branches, gen_data),
+        blocks::Op::Batch(batch) => gen_batch_rows(batch, gen_data),
+        blocks::Op::InList(expr) => {
+            let builder_ident = &gen_data.builder_ident;
+            quote! {
+                #builder_ident.push_in_list(#expr);
+            }
+        }
+        blocks::Op::Splice(expr) => {
+            let builder_ident = &gen_data.builder_ident;
+            quote! {
+                #builder_ident.splice(#expr);
+            }
+        }
+        blocks::Op::Group(group) => gen_group(group, gen_data),
+        blocks::Op::OrderBy(order_by) => {
+            let builder_ident = &gen_data.builder_ident;
+            let terms = order_by.terms.into_iter().enumerate().map(|(index, term)| {
+                let separator = if index > 0 {
+                    Some(quote! { #builder_ident.push_sql(", "); })
+                } else {
+                    None
+                };
+                quote! {
+                    #separator
+                    #builder_ident.push_order_term(#term);
+                }
+            });
+            quote! {
+                #builder_ident.push_order_by();
+                #(#terms)*
+            }
+        }
+    }
+}
+
+fn gen_group(group: blocks::Group, gen_data: &GenData) -> TokenStream {
+    let builder_ident = &gen_data.builder_ident;
+    let group_kind_path = &gen_data.group_kind_path;
+    let kind = match group.kind {
+        parse::GroupKind::Where => quote! { Where },
+        parse::GroupKind::Or => quote! { Or },
+        parse::GroupKind::Set => quote! { Set },
+    };
+    let items = group.items.into_iter().map(|item| {
+        let item = gen_blocks(item, gen_data);
+        quote! {
+            #builder_ident.next_group_item();
+            #item
+        }
+    });
+
+    quote! {
+        #builder_ident.open_group(#group_kind_path::#kind);
+        #(#items)*
+        #builder_ident.close_group();
+    }
+}
+
+/// Reject what the runtime can't render: `where` or `set` inside another
+/// group, and groups nested too deeply.
+fn check_groups(blocks: &[blocks::Block], depth: usize) -> syn::Result<()> {
+    for block in blocks {
+        match &block.op {
+            blocks::Op::Push(_)
+            | blocks::Op::InList(_)
+            | blocks::Op::Splice(_)
+            | blocks::Op::OrderBy(_) => {}
+            blocks::Op::Branch(branches) => {
+                for branch in branches {
+                    check_groups(&branch.then, depth)?;
+                }
+            }
+            blocks::Op::Batch(batch) => check_groups(&batch.row, depth)?,
+            blocks::Op::Group(group) => {
+                if group.kind != parse::GroupKind::Or && depth > 0 {
+                    return Err(syn::Error::new(
+                        group.span,
+                        format!(
+                            "a `{}` group can't be inside another group",
+                            group.kind.keyword()
+                        ),
+                    ));
+                }
+                if depth == parse::MAX_GROUP_DEPTH {
+                    return Err(syn::Error::new(
+                        group.span,
+                        format!(
+                            "groups can't be nested more than {} deep",
+                            parse::MAX_GROUP_DEPTH
+                        ),
+                    ));
+                }
+                for item in &group.items {
+                    check_groups(item, depth + 1)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn batch_rows_ident() -> proc_macro2::Ident {
+    proc_macro2::Ident::new("rows", proc_macro2::Span::mixed_site())
+}
+
+fn batch_capacity_ident() -> proc_macro2::Ident {
+    proc_macro2::Ident::new("capacity", proc_macro2::Span::mixed_site())
+}
+
+/// Locals hoisted out of the chunks of a batch, numbered across the invocation.
+#[derive(Default)]
+struct Hoisted {
+    binds: usize,
+    branches: usize,
+}
+
+impl Hoisted {
+    fn ident(prefix: &str, index: usize, span: proc_macro2::Span) -> proc_macro2::Ident {
+        proc_macro2::Ident::new(
+            &format!("{}_{}", prefix, index),
+            proc_macro2::Span::mixed_site().located_at(span),
+        )
+    }
+
+    /// Move what is around a batch out of the chunks, into `eval` run once before
+    /// them: binds become locals the chunks bind a reference to, and each branch
+    /// chain is decided once, the chunks taking the branch it recorded. Binds in
+    /// a branch are evaluated when it is taken, with what it matched.
+    ///
+    /// `decls` declares what `eval` sets inside branches, so the chunks see it.
+    fn hoist(
+        &mut self,
+        blocks: &mut [blocks::Block],
+        decls: &mut Vec<TokenStream>,
+        eval: &mut Vec<TokenStream>,
+        in_branch: bool,
+    ) {
+        use syn::spanned::Spanned;
+
+        for block in blocks {
+            match &mut block.op {
+                blocks::Op::Push(pushes) => {
+                    for push in pushes {
+                        if let blocks::Push::Bind(expr) = push {
+                            let span = expr.span();
+                            let ident = Self::ident("bind", self.binds, span);
+                            self.binds += 1;
+                            if in_branch {
+                                decls.push(
+                                    quote! { let mut #ident = ::core::option::Option::None; },
+                                );
+                                eval.push(quote! { #ident = ::core::option::Option::Some(#expr); });
+                                **expr = syn::parse_quote_spanned! {span=>
+                                    #ident.as_ref().unwrap()
+                                };
+                            } else {
+                                eval.push(quote! { let #ident = #expr; });
+                                **expr = syn::parse_quote_spanned! {span=> &#ident };
+                            }
+                        }
+                    }
+                }
+                blocks::Op::Branch(branches) => {
+                    let span = branches[0].keywords.span();
+                    let ident = Self::ident("branch", self.branches, span);
+                    self.branches += 1;
+                    let mut branch_decls = vec![];
+                    let mut chain = vec![];
+                    for (index, branch) in branches.iter_mut().enumerate() {
+                        let mut then = vec![];
+                        self.hoist(&mut branch.then, &mut branch_decls, &mut then, true);
+                        let keywords = &branch.keywords;
+                        let cond = branch.cond.as_ref().map(|cond| quote! { #cond });
+                        chain.push(quote! {
+                            #keywords #cond {
+                                #ident = #index;
+                                #(#then)*
+                            }
+                        });
+                        if branch.cond.is_some() {
+                            branch.cond = Some(Box::new(syn::parse_quote! { #ident == #index }));
+                        }
+                    }
+                    let decl = quote! { let mut #ident = usize::MAX; };
+                    if in_branch {
+                        decls.push(decl);
+                        decls.append(&mut branch_decls);
+                    } else {
+                        eval.push(decl);
+                        eval.append(&mut branch_decls);
+                    }
+                    eval.push(quote! { #(#chain)* });
+                }
+                blocks::Op::Group(group) => {
+                    for item in &mut group.items {
+                        self.hoist(item, decls, eval, in_branch);
+                    }
+                }
+                blocks::Op::Batch(_)
+                | blocks::Op::InList(_)
+                | blocks::Op::Splice(_)
+                | blocks::Op::OrderBy(_) => {}
+            }
+        }
+    }
+}
+
+/// Render as many rows as the current chunk has capacity for, comma separated.
+fn gen_batch_rows(batch: blocks::Batch, gen_data: &GenData) -> TokenStream {
+    let builder_ident = &gen_data.builder_ident;
+    let rows_ident = batch_rows_ident();
+    let capacity_ident = batch_capacity_ident();
+    let count_ident = proc_macro2::Ident::new("count", proc_macro2::Span::mixed_site());
+    let pat = batch.pat;
+    let shape: Vec<String> = batch
+        .row
+        .iter()
+        .filter_map(|block| match &block.op {
+            blocks::Op::Push(pushes) => Some(sql_shape(pushes)),
+            _ => None,
+        })
+        .collect();
+    let fold_fingerprint = gen_fold_fingerprint(builder_ident, &shape.join(" "));
+    let row = gen_blocks(batch.row, gen_data);
+
+    quote! {
+        let mut #count_ident = 0usize;
+        while #count_ident < #capacity_ident {
+            let #pat = match #rows_ident.next() {
+                Some(row) => row,
+                None => break,
+            };
+            if #count_ident > 0 {
+                #builder_ident.push_sql(", ");
+            }
+            #row
+            #count_ident += 1;
+        }
+        #fold_fingerprint
+    }
+}
+
+/// Find the single top-level batch, if any. Batches can't be nested in other
+/// constructs because everything around them is repeated for each chunk.
+fn find_batch(blocks: &[blocks::Block]) -> syn::Result<Option<usize>> {
+    fn reject_nested(blocks: &[blocks::Block]) -> syn::Result<()> {
+        for block in blocks {
+            match &block.op {
+                blocks::Op::Push(_)
+                | blocks::Op::InList(_)
+                | blocks::Op::Splice(_)
+                | blocks::Op::OrderBy(_) => {}
+                blocks::Op::Branch(branches) => {
+                    for branch in branches {
+                        reject_nested(&branch.then)?;
+                    }
+                }
+                blocks::Op::Group(group) => {
+                    for item in &group.items {
+                        reject_nested(item)?;
+                    }
+                }
+                blocks::Op::Batch(batch) => {
+                    return Err(syn::Error::new_spanned(
+                        batch.for_token,
+                        "a batch `for` must be at the top level of build_query!",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// What in `blocks` binds as many parameters as it is given at runtime, if
+    /// anything, which leaves no bound to keep free after the rows.
+    fn unbounded(blocks: &[blocks::Block]) -> Option<&'static str> {
+        blocks.iter().find_map(|block| match &block.op {
+            blocks::Op::InList(_) => Some("an IN list"),
+            blocks::Op::Splice(_) => Some("a spliced query"),
+            blocks::Op::Branch(branches) => {
+                branches.iter().find_map(|branch| unbounded(&branch.then))
+            }
+            blocks::Op::Group(group) => group.items.iter().find_map(|item| unbounded(item)),
+            blocks::Op::Push(_) | blocks::Op::Batch(_) | blocks::Op::OrderBy(_) => None,
+        })
+    }
+
+    let mut found: Option<usize> = None;
+    for (index, block) in blocks.iter().enumerate() {
+        if let Some(index) = found {
+            if let blocks::Op::Batch(batch) = &blocks[index].op {
+                if let Some(what) = unbounded(std::slice::from_ref(block)) {
+                    return Err(syn::Error::new_spanned(
+                        batch.for_token,
+                        format!("{} can't follow a batch `for`", what),
+                    ));
+                }
+            }
+        }
+        match &block.op {
+            blocks::Op::Push(_)
+            | blocks::Op::OrderBy(_)
+            | blocks::Op::InList(_)
+            | blocks::Op::Splice(_) => {}
+            blocks::Op::Branch(branches) => {
+                for branch in branches {
+                    reject_nested(&branch.then)?;
+                }
+            }
+            blocks::Op::Group(group) => {
+                for item in &group.items {
+                    reject_nested(item)?;
+                }
+            }
+            blocks::Op::Batch(batch) => {
+                if found.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        batch.for_token,
+                        "only one batch `for` is allowed per build_query!",
+                    ));
+                }
+                if batch
+                    .row
+                    .iter()
+                    .any(|block| !matches!(block.op, blocks::Op::Push(_)))
+                {
+                    return Err(syn::Error::new_spanned(
+                        batch.for_token,
+                        "batch rows can only contain literals and binds",
+                    ));
+                }
+                found = Some(index);
+            }
+        }
     }
+    Ok(found)
+}
+
+/// Whether `blocks` can fail to build for what they are given at runtime: an
+/// IN list can be empty, and so can a `set` group none of whose items always
+/// renders. Invocations with these return a `Result` as with `#![try_build]`.
+fn fallible(blocks: &[blocks::Block]) -> bool {
+    fn always_renders(item: &[blocks::Block]) -> bool {
+        item.iter().any(|block| match &block.op {
+            blocks::Op::Push(pushes) => pushes.iter().any(|push| match push {
+                blocks::Push::Lit(lit_str) => !lit_str.value().is_empty(),
+                blocks::Push::Bind(_) => true,
+                blocks::Push::Empty => false,
+            }),
+            _ => false,
+        })
+    }
+
+    blocks.iter().any(|block| match &block.op {
+        blocks::Op::InList(_) => true,
+        blocks::Op::Branch(branches) => branches.iter().any(|branch| fallible(&branch.then)),
+        blocks::Op::Batch(batch) => fallible(&batch.row),
+        blocks::Op::Group(group) => {
+            (group.kind == parse::GroupKind::Set
+                && !group.items.iter().any(|item| always_renders(item)))
+                || group.items.iter().any(|item| fallible(item))
+        }
+        blocks::Op::Push(_) | blocks::Op::Splice(_) | blocks::Op::OrderBy(_) => false,
+    })
 }
 
 fn gen_blocks(blocks: Vec<blocks::Block>, gen_data: &GenData) -> TokenStream {
     let output: Vec<_> = blocks
         .into_iter()
-        .map(|block| gen_block(block, &gen_data))
+        .map(|block| gen_block(block, gen_data))
         .collect();
 
     quote! {
@@ -155,31 +770,259 @@ fn gen_blocks(blocks: Vec<blocks::Block>, gen_data: &GenData) -> TokenStream {
     }
 }
 
-pub fn codegen(ast: parse::BuilderAST) -> TokenStream {
+/// Write the manifest of this invocation, if a manifest directory is configured.
+fn write_manifest(
+    blocks: &[blocks::Block],
+    call_site: &manifest::CallSite,
+    gen_data: &GenData,
+) -> syn::Result<()> {
+    let dir = match std::env::var_os(manifest::DIR_ENV) {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => return Ok(()),
+    };
+    let (variants, truncated) = variants::enumerate(blocks, variants::MAX_VARIANTS);
+    let manifest = manifest::Manifest {
+        krate: std::env::var("CARGO_CRATE_NAME").unwrap_or_default(),
+        call_site,
+        dialect: variants::dialect_name(&gen_data.dialect).unwrap_or_default(),
+        style: variants::PlaceholderStyle::from_dialect(&gen_data.dialect),
+        variants: &variants,
+        truncated,
+    };
+    manifest.write(&dir).map_err(|err| {
+        syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!(
+                "failed to write query manifest into {}: {}",
+                dir.display(),
+                err
+            ),
+        )
+    })
+}
+
+// Seeded by the file only, so edits elsewhere in it don't change fingerprints.
+// `#![fingerprint("name")]` tells apart invocations of the same SQL. Variant ids
+// start from the same seed.
+fn gen_seed(gen_data: &GenData) -> TokenStream {
+    let builder_ident = &gen_data.builder_ident;
+    let fingerprint_path = &gen_data.fingerprint_path;
+    let seed = match &gen_data.fingerprint_name {
+        Some(name) => quote! {
+            #fingerprint_path::EMPTY
+                .fold(#fingerprint_path::hash(::core::file!().as_bytes()))
+                .fold(#fingerprint_path::hash(#name.as_bytes()))
+                .0
+        },
+        None => quote! { #fingerprint_path::hash(::core::file!().as_bytes()) },
+    };
+    quote! {
+        {
+            const SEED: u64 = #seed;
+            #builder_ident.fold_fingerprint(SEED);
+            #builder_ident.fold_variant(SEED);
+        }
+    }
+}
+
+/// `sql_fragment!`: the body of a `build_query!` rendered into a `SqlFragment`,
+/// which leaves numbering placeholders to the query it is spliced into.
+pub fn codegen_fragment(
+    ast: parse::BuilderAST,
+    call_site: Option<manifest::CallSite>,
+) -> syn::Result<TokenStream> {
+    if let Some(attr) = ast
+        .attrs
+        .iter()
+        .find(|attr| !attr.path.is_ident("args") && !attr.path.is_ident("fingerprint"))
+    {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "sql_fragment only takes `args` and `fingerprint` attributes",
+        ));
+    }
+    let gen_data = GenData::from_attrs(&ast.attrs, call_site)?;
     let blocks = blocks::create_blocks(
         ast.constituents,
         blocks::Parent::root(),
         &mut blocks::Counter::new(),
-    );
-    let gen_data = GenData::default();
-    let sql_fmt_fns = gen_sql_fmt_fns(&blocks, &gen_data);
-    let statements = gen_blocks(blocks, &gen_data);
+    )?;
+    check_groups(&blocks, 0)?;
+    if let Some(index) = find_batch(&blocks)? {
+        if let blocks::Op::Batch(batch) = &blocks[index].op {
+            return Err(syn::Error::new_spanned(
+                batch.for_token,
+                "a batch `for` can't be in a fragment",
+            ));
+        }
+    }
+    let sql_fmt_fns = gen_sql_fmt_fns(&blocks, true, &mut SourceContext::default(), &gen_data);
 
     let builder_ident = &gen_data.builder_ident;
+    let builder_path = &gen_data.builder_path;
+    let args = &gen_data.args;
+    let seed = gen_seed(&gen_data);
+    let statements = gen_blocks(blocks, &gen_data);
 
-    let builder_path: syn::Path = syn::parse_str("sql_builder_test::Builder").unwrap();
-
-    quote! {
+    Ok(quote! {
         {
-            use std::fmt::Write;
-
             #sql_fmt_fns
 
-            let mut #builder_ident = #builder_path::new();
+            let mut #builder_ident = #builder_path::<_, _, #args>::fragment();
+            #seed
 
             #statements
 
-            #builder_ident.build()
+            #builder_ident.into_fragment()
+        }
+    })
+}
+
+pub fn codegen(
+    ast: parse::BuilderAST,
+    call_site: Option<manifest::CallSite>,
+) -> syn::Result<TokenStream> {
+    let mut gen_data = GenData::from_attrs(&ast.attrs, call_site)?;
+    let blocks = blocks::create_blocks(
+        ast.constituents,
+        blocks::Parent::root(),
+        &mut blocks::Counter::new(),
+    )?;
+    gen_data.try_build |= fallible(&blocks);
+    check_groups(&blocks, 0)?;
+    if let Some(call_site) = &gen_data.call_site {
+        write_manifest(&blocks, call_site, &gen_data)?;
+    }
+    let sql_fmt_fns = gen_sql_fmt_fns(&blocks, true, &mut SourceContext::default(), &gen_data);
+
+    let builder_ident = &gen_data.builder_ident;
+    let builder_path = &gen_data.builder_path;
+    let sink = &gen_data.sink;
+    let dialect = &gen_data.dialect;
+    let args = &gen_data.args;
+
+    let new_builder = quote! { #builder_path::<_, #dialect, #args>::with_sink(#sink) };
+    let seed = gen_seed(&gen_data);
+    let call_site = gen_data.call_site_location();
+    let call_site = quote! { &#call_site };
+    let build = if gen_data.try_build {
+        quote! { try_build_at }
+    } else {
+        quote! { build_at }
+    };
+
+    match find_batch(&blocks)? {
+        None => {
+            let statements = gen_blocks(blocks, &gen_data);
+
+            Ok(quote! {
+                {
+                    #sql_fmt_fns
+
+                    let mut #builder_ident = #new_builder;
+                    #seed
+
+                    #statements
+
+                    #builder_ident.#build(#call_site)
+                }
+            })
+        }
+        Some(index) => {
+            let mut prefix = blocks;
+            let mut suffix = prefix.split_off(index);
+            let batch = match suffix.remove(0).op {
+                blocks::Op::Batch(batch) => batch,
+                _ => unreachable!(),
+            };
+
+            let row_params = blocks::max_binds(&batch.row);
+            let suffix_params = blocks::max_binds(&suffix);
+            let expr = &batch.expr;
+
+            let vec_path = &gen_data.vec_path;
+            let rows_ident = batch_rows_ident();
+            let capacity_ident = batch_capacity_ident();
+            let queries_ident = proc_macro2::Ident::new("queries", proc_macro2::Span::mixed_site());
+
+            let expr = quote! { ::core::iter::IntoIterator::into_iter(#expr).peekable() };
+            let mut hoisted = Hoisted::default();
+            let mut prefix_binds = vec![];
+            hoisted.hoist(&mut prefix, &mut vec![], &mut prefix_binds, false);
+            let mut suffix_binds = vec![];
+            hoisted.hoist(&mut suffix, &mut vec![], &mut suffix_binds, false);
+            let prefix = gen_blocks(prefix, &gen_data);
+            let rows = gen_batch_rows(batch, &gen_data);
+            let suffix = gen_blocks(suffix, &gen_data);
+
+            let on_capacity_error = if gen_data.try_build {
+                quote! {
+                    let #capacity_ident = match #capacity_ident {
+                        Ok(capacity) => capacity,
+                        Err(err) => break Err(err),
+                    };
+                }
+            } else {
+                quote! {
+                    let #capacity_ident = match #capacity_ident {
+                        Ok(capacity) => capacity,
+                        Err(err) => ::core::panic!("{}", err),
+                    };
+                }
+            };
+
+            let chunk = quote! {
+                let mut #builder_ident = #new_builder;
+                #seed
+
+                #prefix
+
+                let #capacity_ident = #builder_ident.batch_capacity(#row_params, #suffix_params);
+                #on_capacity_error
+
+                #rows
+                #suffix
+            };
+
+            let chunks = if gen_data.try_build {
+                quote! {
+                    loop {
+                        if #rows_ident.peek().is_none() {
+                            break Ok(#queries_ident);
+                        }
+
+                        #chunk
+
+                        match #builder_ident.try_build_at(#call_site) {
+                            Ok(query) => #queries_ident.push(query),
+                            Err(err) => break Err(err),
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    while #rows_ident.peek().is_some() {
+                        #chunk
+
+                        #queries_ident.push(#builder_ident.build_at(#call_site));
+                    }
+
+                    #queries_ident
+                }
+            };
+
+            Ok(quote! {
+                {
+                    #sql_fmt_fns
+
+                    let mut #queries_ident = #vec_path::new();
+                    #(#prefix_binds)*
+                    let mut #rows_ident = #expr;
+                    #(#suffix_binds)*
+
+                    #chunks
+                }
+            })
         }
     }
 }
@@ -193,7 +1036,8 @@ mod tests {
             ast.constituents,
             blocks::Parent::root(),
             &mut blocks::Counter::new(),
-        );
+        )
+        .unwrap();
         gen_blocks(blocks, &GenData::default())
     }
 
@@ -205,7 +1049,7 @@ mod tests {
             })
             .unwrap(),
         );
-        assert_eq!(format!("{}", stream), "sql_fmt_0 ( & mut builder ) ;");
+        assert_eq!(format!("{}", stream), "sql_fmt_0 (& mut builder) ;");
     }
 
     #[test]
@@ -218,7 +1062,7 @@ mod tests {
         );
         assert_eq!(
             format!("{}", stream),
-            "sql_fmt_0 ( & mut builder ) ; builder . push_bind_arg ( 42 ) ;"
+            "sql_fmt_0 (& mut builder) ; builder . push_bind_arg (42) ;"
         );
     }
 
@@ -238,13 +1082,33 @@ mod tests {
         assert_eq!(
             format!("{}", stream),
             concat!(
-                "sql_fmt_0 ( & mut builder ) ; ",
-                "if true { sql_fmt_1_0_0 ( & mut builder ) ; builder . push_bind_arg ( 1 ) ; } ",
-                "else { sql_fmt_1_1_0 ( & mut builder ) ; builder . push_bind_arg ( 2 ) ; }"
+                "sql_fmt_0 (& mut builder) ; ",
+                "if true { sql_fmt_1_0_0 (& mut builder) ; builder . push_bind_arg (1) ; } ",
+                "else { sql_fmt_1_1_0 (& mut builder) ; builder . push_bind_arg (2) ; }"
             )
         );
     }
 
+    #[test]
+    fn sql_shape_normalizes_whitespace_and_case() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT *\n  FROM t" " WHERE a = " a "AND b =" b
+        })
+        .unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let pushes = match &blocks[0].op {
+            blocks::Op::Push(pushes) => pushes,
+            _ => panic!("expected a push block"),
+        };
+        assert_eq!(sql_shape(pushes), "select * from t where a = ? and b = ?");
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
     #[test]
     fn experiment() {
         use std::fmt::Write;
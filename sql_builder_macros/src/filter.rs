@@ -0,0 +1,162 @@
+//! `#[derive(SqlFilter)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::attrs;
+use crate::codegen::fnv1a;
+
+/// The single type argument of `ty` if it is spelled `<name><...>`.
+fn type_argument<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) => match arguments.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+const OPERATORS: &[(&str, &str)] = &[
+    ("eq", " = "),
+    ("ne", " <> "),
+    ("gt", " > "),
+    ("gte", " >= "),
+    ("lt", " < "),
+    ("lte", " <= "),
+    ("like", " LIKE "),
+    ("ilike", " ILIKE "),
+];
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    if let Some(entry) = attrs::entries(&input.attrs)?.first() {
+        return Err(entry.unknown());
+    }
+
+    let mut predicates = vec![];
+    for field in attrs::named_fields(&input, "SqlFilter")? {
+        let mut rename = None;
+        let mut skip = false;
+        let mut operator = None;
+        for entry in attrs::entries(&field.attrs)? {
+            if entry.is("rename") {
+                rename = Some(entry.value()?);
+            } else if entry.is("skip") {
+                skip = entry.flag()?;
+            } else if let Some((_, sql)) = OPERATORS.iter().find(|(name, _)| entry.is(name)) {
+                if operator.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        &entry.path,
+                        "only one operator per field",
+                    ));
+                }
+                operator = Some(*sql);
+                if entry.value.is_some() {
+                    rename = Some(entry.value()?);
+                }
+            } else {
+                return Err(entry.unknown());
+            }
+        }
+        if skip {
+            continue;
+        }
+
+        let column = attrs::column(field, rename);
+        let member = field.ident.as_ref().unwrap();
+        let optional = type_argument(&field.ty, "Option");
+        let list = type_argument(optional.unwrap_or(&field.ty), "Vec").is_some();
+
+        let (sql, push) = if list {
+            if operator.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "`Vec` fields are always matched with IN",
+                ));
+            }
+            (
+                format!("{} IN ", column),
+                quote! { builder.push_in_list(value.iter()); },
+            )
+        } else if operator == Some(" ILIKE ") {
+            // SQLite has no `ILIKE`, so the dialect renders the operator.
+            (
+                column,
+                quote! {
+                    builder.push_sql(<D as sql_builder_test::Dialect>::ILIKE);
+                    builder.push_sql_arg();
+                    builder.push_bind_arg(value);
+                },
+            )
+        } else {
+            (
+                format!("{}{}", column, operator.unwrap_or(" = ")),
+                quote! {
+                    builder.push_sql_arg();
+                    builder.push_bind_arg(value);
+                },
+            )
+        };
+        let hash = fnv1a(sql.to_lowercase().as_bytes());
+        let predicate = quote! {
+            builder.fold_fingerprint(#hash);
+            builder.fold_variant(#hash);
+            builder.push_sql(separator);
+            builder.push_sql(#sql);
+            #push
+            separator = " AND ";
+        };
+        let body = if list {
+            quote! {
+                if !value.is_empty() {
+                    #predicate
+                }
+            }
+        } else {
+            predicate
+        };
+        predicates.push(match optional {
+            Some(_) => quote! {
+                if let Some(value) = &self.#member {
+                    #body
+                }
+            },
+            None => quote! {
+                {
+                    let value = &self.#member;
+                    #body
+                }
+            },
+        });
+    }
+
+    let ident = &input.ident;
+    let seed = fnv1a(ident.to_string().as_bytes());
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics sql_builder_test::SqlFilter for #ident #ty_generics #where_clause {
+            #[allow(unused_assignments, unused_mut)]
+            fn push_filter<
+                S: sql_builder_test::Sink,
+                D: sql_builder_test::Dialect,
+                A: sql_builder_test::Arguments,
+            >(
+                &self,
+                builder: &mut sql_builder_test::Builder<S, D, A>,
+            ) {
+                builder.fold_fingerprint(#seed);
+                builder.fold_variant(#seed);
+                let mut separator = "";
+                #(#predicates)*
+            }
+        }
+    })
+}
@@ -0,0 +1,144 @@
+//! `#[derive(SqlInsert)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::attrs;
+
+struct Column {
+    name: String,
+    member: syn::Ident,
+    /// `#[sql(default)]`: an `Option` left out when `None`, for the column default.
+    default: bool,
+}
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let mut returning = None;
+    let entries = attrs::entries(&input.attrs)?;
+    for entry in &entries {
+        if entry.is("returning") {
+            returning = Some(entry.value()?.value());
+        } else if !entry.is("table") {
+            return Err(entry.unknown());
+        }
+    }
+    let table = attrs::table(&input, &entries)?;
+
+    let mut columns = vec![];
+    for field in attrs::named_fields(&input, "SqlInsert")? {
+        let mut rename = None;
+        let mut skip = false;
+        let mut default = false;
+        for entry in attrs::entries(&field.attrs)? {
+            if entry.is("rename") {
+                rename = Some(entry.value()?);
+            } else if entry.is("skip") {
+                skip = entry.flag()?;
+            } else if entry.is("default") {
+                default = entry.flag()?;
+            } else {
+                return Err(entry.unknown());
+            }
+        }
+        if !skip {
+            columns.push(Column {
+                name: attrs::column(field, rename),
+                member: field.ident.clone().unwrap(),
+                default,
+            });
+        }
+    }
+
+    let present = if columns.iter().any(|column| !column.default) {
+        quote! { true }
+    } else {
+        let checks = columns.iter().map(|column| {
+            let member = &column.member;
+            quote! { self.#member.is_some() }
+        });
+        quote! { false #(|| #checks)* }
+    };
+
+    let column_names = columns.iter().map(|column| {
+        let name = &column.name;
+        let push = quote! {
+            builder.push_sql(separator);
+            builder.push_sql(#name);
+            separator = ", ";
+        };
+        if column.default {
+            let member = &column.member;
+            let hash = crate::codegen::fnv1a(name.as_bytes());
+            quote! {
+                if self.#member.is_some() {
+                    builder.fold_fingerprint(#hash);
+                    builder.fold_variant(#hash);
+                    #push
+                }
+            }
+        } else {
+            push
+        }
+    });
+    let values = columns.iter().map(|column| {
+        let member = &column.member;
+        let push = |value: TokenStream| {
+            quote! {
+                builder.push_sql(separator);
+                builder.push_sql_arg();
+                builder.push_bind_arg(#value);
+                separator = ", ";
+            }
+        };
+        if column.default {
+            let push = push(quote! { value });
+            quote! {
+                if let Some(value) = &self.#member {
+                    #push
+                }
+            }
+        } else {
+            push(quote! { &self.#member })
+        }
+    });
+
+    let insert = format!("INSERT INTO {}", table);
+    let shape = crate::codegen::fnv1a(insert.to_lowercase().as_bytes());
+    let returning = returning.map(|returning| {
+        let returning = format!(" RETURNING {}", returning);
+        quote! { builder.push_sql(#returning); }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics sql_builder_test::SqlInsert for #ident #ty_generics #where_clause {
+            #[allow(unused_assignments)]
+            fn push_insert<
+                S: sql_builder_test::Sink,
+                D: sql_builder_test::Dialect,
+                A: sql_builder_test::Arguments,
+            >(
+                &self,
+                builder: &mut sql_builder_test::Builder<S, D, A>,
+            ) {
+                builder.fold_fingerprint(#shape);
+                builder.fold_variant(#shape);
+                builder.push_sql(#insert);
+                if #present {
+                    let mut separator = "";
+                    builder.push_sql(" (");
+                    #(#column_names)*
+                    builder.push_sql(") VALUES (");
+                    separator = "";
+                    #(#values)*
+                    builder.push_sql(")");
+                } else {
+                    builder.push_sql(" DEFAULT VALUES");
+                }
+                #returning
+            }
+        }
+    })
+}
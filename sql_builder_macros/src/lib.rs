@@ -3,13 +3,72 @@
 use proc_macro::TokenStream;
 use proc_macro_hack::proc_macro_hack;
 
-mod blocks;
+use sql_builder_syntax::{blocks, parse, scan, variants};
+
+mod attrs;
 mod builder;
 mod codegen;
-mod parse;
+mod filter;
+mod insert;
+mod manifest;
+mod order;
+mod update;
+
+/// Where the invocation of a macro with `input` is, from the first token of the
+/// input: proc_macro_hack makes `Span::call_site()` point into the runtime crate.
+fn call_site(input: &TokenStream) -> Option<manifest::CallSite> {
+    let tokens = proc_macro2::TokenStream::from(input.clone());
+    let (line, column) = scan::call_site(&tokens)?;
+    let file = tokens.into_iter().next()?.span().file();
+    Some(manifest::CallSite { file, line, column })
+}
 
 #[proc_macro_hack]
 pub fn build_query(input: TokenStream) -> TokenStream {
+    let call_site = call_site(&input);
+    let ast: parse::BuilderAST = syn::parse_macro_input!(input);
+    codegen::codegen(ast, call_site)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_hack]
+pub fn sql_fragment(input: TokenStream) -> TokenStream {
+    let call_site = call_site(&input);
     let ast: parse::BuilderAST = syn::parse_macro_input!(input);
-    codegen::codegen(ast).into()
+    codegen::codegen_fragment(ast, call_site)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(SqlInsert, attributes(sql))]
+pub fn derive_sql_insert(input: TokenStream) -> TokenStream {
+    let input: syn::DeriveInput = syn::parse_macro_input!(input);
+    insert::derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(SqlUpdate, attributes(sql))]
+pub fn derive_sql_update(input: TokenStream) -> TokenStream {
+    let input: syn::DeriveInput = syn::parse_macro_input!(input);
+    update::derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(SqlFilter, attributes(sql))]
+pub fn derive_sql_filter(input: TokenStream) -> TokenStream {
+    let input: syn::DeriveInput = syn::parse_macro_input!(input);
+    filter::derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(OrderBy, attributes(sql))]
+pub fn derive_order_by(input: TokenStream) -> TokenStream {
+    let input: syn::DeriveInput = syn::parse_macro_input!(input);
+    order::derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::variants;
+
+/// The environment variable naming the directory manifests are written into.
+/// Nothing is written when it isn't set.
+pub const DIR_ENV: &str = "SQL_BUILDER_MANIFEST_DIR";
+
+pub struct CallSite {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct Manifest<'a> {
+    pub krate: String,
+    pub call_site: &'a CallSite,
+    pub dialect: String,
+    pub style: variants::PlaceholderStyle,
+    pub variants: &'a [variants::Variant],
+    pub truncated: bool,
+}
+
+impl Manifest<'_> {
+    /// `<dir>/<crate>/<file>-<line>-<column>.json`, with path separators in the
+    /// source file name replaced by `_`.
+    pub fn path(&self, dir: &Path) -> PathBuf {
+        let file: String = self
+            .call_site
+            .file
+            .chars()
+            .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+            .collect();
+        dir.join(&self.krate).join(format!(
+            "{}-{}-{}.json",
+            file, self.call_site.line, self.call_site.column
+        ))
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        writeln!(json, "  \"crate\": {},", quote_json(&self.krate)).unwrap();
+        writeln!(json, "  \"file\": {},", quote_json(&self.call_site.file)).unwrap();
+        writeln!(json, "  \"line\": {},", self.call_site.line).unwrap();
+        writeln!(json, "  \"column\": {},", self.call_site.column).unwrap();
+        writeln!(json, "  \"dialect\": {},", quote_json(&self.dialect)).unwrap();
+        writeln!(json, "  \"truncated\": {},", self.truncated).unwrap();
+        json.push_str("  \"variants\": [");
+        for (index, variant) in self.variants.iter().enumerate() {
+            let rendered = variant.render(self.style);
+            if index > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "\n    {{ \"sql\": {}, \"binds\": {}, \"repeated\": {} }}",
+                quote_json(&rendered.sql),
+                rendered.binds,
+                rendered.repeated
+            )
+            .unwrap();
+        }
+        if !self.variants.is_empty() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+
+    pub fn write(&self, dir: &Path) -> std::io::Result<()> {
+        let path = self.path(dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json())
+    }
+}
+
+fn quote_json(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use variants::{Piece, PlaceholderStyle, Variant};
+
+    #[test]
+    fn json() {
+        let call_site = CallSite {
+            file: "src/repo.rs".to_owned(),
+            line: 120,
+            column: 5,
+        };
+        let variants = vec![
+            Variant {
+                pieces: vec![
+                    Piece::Sql("SELECT \"a\" FROM t WHERE a = ".to_owned()),
+                    Piece::Arg,
+                ],
+            },
+            Variant {
+                pieces: vec![Piece::Sql("SELECT \"a\" FROM t".to_owned())],
+            },
+        ];
+        let manifest = Manifest {
+            krate: "app".to_owned(),
+            call_site: &call_site,
+            dialect: "Postgres".to_owned(),
+            style: PlaceholderStyle::DollarFromOne,
+            variants: &variants,
+            truncated: false,
+        };
+
+        assert_eq!(
+            manifest.path(Path::new("target/queries")),
+            Path::new("target/queries/app/src_repo.rs-120-5.json")
+        );
+        assert_eq!(
+            manifest.to_json(),
+            concat!(
+                "{\n",
+                "  \"crate\": \"app\",\n",
+                "  \"file\": \"src/repo.rs\",\n",
+                "  \"line\": 120,\n",
+                "  \"column\": 5,\n",
+                "  \"dialect\": \"Postgres\",\n",
+                "  \"truncated\": false,\n",
+                "  \"variants\": [\n",
+                "    { \"sql\": \"SELECT \\\"a\\\" FROM t WHERE a = $1\", \"binds\": 1, \"repeated\": false },\n",
+                "    { \"sql\": \"SELECT \\\"a\\\" FROM t\", \"binds\": 0, \"repeated\": false }\n",
+                "  ]\n",
+                "}\n",
+            )
+        );
+    }
+}
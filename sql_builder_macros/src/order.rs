@@ -0,0 +1,91 @@
+//! `#[derive(OrderBy)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::attrs;
+
+/// `CreatedAt` as `created_at`.
+fn snake_case(ident: &syn::Ident) -> String {
+    let mut snake = String::new();
+    for (index, c) in ident
+        .to_string()
+        .trim_start_matches("r#")
+        .chars()
+        .enumerate()
+    {
+        if c.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    if let Some(entry) = attrs::entries(&input.attrs)?.first() {
+        return Err(entry.unknown());
+    }
+    let variants = match &input.data {
+        syn::Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "OrderBy can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut columns = vec![];
+    let mut names = vec![];
+    for variant in variants {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                &variant.fields,
+                "OrderBy variants can't have fields",
+            ));
+        }
+        let name = snake_case(&variant.ident);
+        let mut column = name.clone();
+        for entry in attrs::entries(&variant.attrs)? {
+            if entry.is("rename") {
+                column = entry.value()?.value();
+            } else {
+                return Err(entry.unknown());
+            }
+        }
+        let ident = &variant.ident;
+        columns.push(quote! { Self::#ident => #column });
+        names.push(quote! { #name => Ok(Self::#ident) });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics sql_builder_test::__private::Sealed for #ident #ty_generics #where_clause {}
+
+        impl #impl_generics sql_builder_test::OrderBy for #ident #ty_generics #where_clause {
+            fn column(&self) -> &'static str {
+                match *self {
+                    #(#columns,)*
+                }
+            }
+        }
+
+        impl #impl_generics core::str::FromStr for #ident #ty_generics #where_clause {
+            type Err = sql_builder_test::ParseOrderError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    #(#names,)*
+                    _ => Err(sql_builder_test::ParseOrderError),
+                }
+            }
+        }
+    })
+}
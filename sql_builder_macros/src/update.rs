@@ -0,0 +1,128 @@
+//! `#[derive(SqlUpdate)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::attrs;
+use crate::codegen::fnv1a;
+
+/// Whether `ty` is spelled `Option<...>`.
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let mut returning = None;
+    let entries = attrs::entries(&input.attrs)?;
+    for entry in &entries {
+        if entry.is("returning") {
+            returning = Some(entry.value()?.value());
+        } else if !entry.is("table") {
+            return Err(entry.unknown());
+        }
+    }
+    let table = attrs::table(&input, &entries)?;
+
+    let mut assignments = vec![];
+    let mut keys = vec![];
+    for field in attrs::named_fields(&input, "SqlUpdate")? {
+        let mut rename = None;
+        let mut skip = false;
+        let mut key = false;
+        for entry in attrs::entries(&field.attrs)? {
+            if entry.is("rename") {
+                rename = Some(entry.value()?);
+            } else if entry.is("skip") {
+                skip = entry.flag()?;
+            } else if entry.is("key") {
+                key = entry.flag()?;
+            } else {
+                return Err(entry.unknown());
+            }
+        }
+        if skip {
+            continue;
+        }
+        let column = attrs::column(field, rename);
+        let member = field.ident.as_ref().unwrap();
+        let sql = format!("{} = ", column);
+        let hash = fnv1a(column.as_bytes());
+        if key {
+            keys.push(quote! {
+                builder.next_group_item();
+                builder.fold_fingerprint(#hash);
+                builder.fold_variant(#hash);
+                builder.push_sql(#sql);
+                builder.push_sql_arg();
+                builder.push_bind_arg(&self.#member);
+            });
+        } else if is_option(&field.ty) {
+            assignments.push(quote! {
+                builder.next_group_item();
+                if let Some(value) = &self.#member {
+                    builder.fold_fingerprint(#hash);
+                    builder.fold_variant(#hash);
+                    builder.push_sql(#sql);
+                    builder.push_sql_arg();
+                    builder.push_bind_arg(value);
+                }
+            });
+        } else {
+            assignments.push(quote! {
+                builder.next_group_item();
+                builder.fold_fingerprint(#hash);
+                builder.fold_variant(#hash);
+                builder.push_sql(#sql);
+                builder.push_sql_arg();
+                builder.push_bind_arg(&self.#member);
+            });
+        }
+    }
+    if keys.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "SqlUpdate needs at least one `#[sql(key)]` field for the WHERE clause",
+        ));
+    }
+
+    let update = format!("UPDATE {}", table);
+    let shape = fnv1a(update.to_lowercase().as_bytes());
+    let returning = returning.map(|returning| {
+        let returning = format!(" RETURNING {}", returning);
+        quote! { builder.push_sql(#returning); }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics sql_builder_test::SqlUpdate for #ident #ty_generics #where_clause {
+            fn push_update<
+                S: sql_builder_test::Sink,
+                D: sql_builder_test::Dialect,
+                A: sql_builder_test::Arguments,
+            >(
+                &self,
+                builder: &mut sql_builder_test::Builder<S, D, A>,
+            ) {
+                builder.fold_fingerprint(#shape);
+                builder.fold_variant(#shape);
+                builder.push_sql(#update);
+                builder.open_group(sql_builder_test::GroupKind::Set);
+                #(#assignments)*
+                builder.close_group();
+                builder.open_group(sql_builder_test::GroupKind::Where);
+                #(#keys)*
+                builder.close_group();
+                #returning
+            }
+        }
+    })
+}
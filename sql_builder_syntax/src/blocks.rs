@@ -0,0 +1,450 @@
+use std::iter::IntoIterator;
+use std::rc::Rc;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::parse;
+
+#[derive(Default)]
+pub struct Counter(u32);
+
+impl Counter {
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    fn next(&mut self) -> u32 {
+        let next = self.0;
+        self.0 += 1;
+        next
+    }
+}
+
+pub struct NodeId {
+    pub parent: Option<Rc<NodeId>>,
+    pub id: u32,
+    pub local_index: u16,
+}
+
+pub struct Parent(Option<Rc<NodeId>>);
+
+impl Parent {
+    pub fn root() -> Self {
+        Parent(None)
+    }
+
+    fn id(&self) -> Rc<NodeId> {
+        self.0.clone().unwrap()
+    }
+
+    fn child(&self, index: usize, counter: &mut Counter) -> Parent {
+        Self(Some(Rc::new(NodeId {
+            parent: self.0.as_ref().map(|id| id.clone()),
+            id: counter.next(),
+            local_index: index as u16,
+        })))
+    }
+}
+
+pub enum Push {
+    Lit(syn::LitStr),
+    Bind(Box<syn::Expr>),
+    Empty,
+}
+
+pub struct Branch {
+    pub keywords: TokenStream,
+    pub cond: Option<Box<syn::Expr>>,
+    pub then: Vec<Block>,
+}
+
+pub struct Batch {
+    pub for_token: syn::Token![for],
+    pub pat: syn::Pat,
+    pub expr: Box<syn::Expr>,
+    pub row: Vec<Block>,
+}
+
+pub struct Group {
+    pub kind: parse::GroupKind,
+    pub span: proc_macro2::Span,
+    pub items: Vec<Vec<Block>>,
+}
+
+pub enum Op {
+    Push(Vec<Push>),
+    // "Flattened" branch - the length of the vec is the number of possibilities:
+    Branch(Vec<Branch>),
+    Batch(Batch),
+    InList(Box<syn::Expr>),
+    Splice(Box<syn::Expr>),
+    Group(Group),
+    OrderBy(parse::OrderBy),
+}
+
+pub struct Block {
+    pub op: Op,
+    pub id: Rc<NodeId>,
+}
+
+pub fn create_blocks(
+    constituents: Vec<parse::Constituent>,
+    parent: Parent,
+    counter: &mut Counter,
+) -> syn::Result<Vec<Block>> {
+    let mut peek_ast = constituents.into_iter().peekable();
+    let mut pushes = vec![];
+    let mut blocks = vec![];
+
+    loop {
+        match peek_ast.peek() {
+            None => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                }
+                return Ok(blocks);
+            }
+            Some(parse::Constituent::Literal(_)) => {
+                pushes.push(match peek_ast.next().unwrap() {
+                    parse::Constituent::Literal(lit_str) => Push::Lit(lit_str),
+                    _ => panic!(),
+                });
+            }
+            Some(parse::Constituent::Bind(_)) => {
+                pushes.push(match peek_ast.next().unwrap() {
+                    parse::Constituent::Bind(expr) => Push::Bind(Box::new(expr)),
+                    _ => panic!(),
+                });
+            }
+            Some(parse::Constituent::Block(_)) => {
+                pushes.push(match peek_ast.next().unwrap() {
+                    parse::Constituent::Block(_) => Push::Empty,
+                    _ => panic!(),
+                });
+            }
+            Some(parse::Constituent::If(_)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+                let branch_parent = parent.child(blocks.len(), counter);
+
+                let iff = match peek_ast.next().unwrap() {
+                    parse::Constituent::If(iff) => iff,
+                    _ => panic!(),
+                };
+                let if_token = iff.if_token;
+
+                let mut branches = vec![];
+                branches.push(Branch {
+                    keywords: quote! { #if_token },
+                    cond: Some(iff.cond),
+                    then: create_blocks(
+                        iff.then_branch.constituents,
+                        branch_parent.child(branches.len(), counter),
+                        counter,
+                    )?,
+                });
+
+                let mut next = iff.else_branch;
+
+                while let Some(else_branch) = next {
+                    match else_branch {
+                        parse::Else::If(else_token, iff) => {
+                            let if_token = iff.if_token;
+                            branches.push(Branch {
+                                keywords: quote! { #else_token #if_token },
+                                cond: Some(iff.cond),
+                                then: create_blocks(
+                                    iff.then_branch.constituents,
+                                    branch_parent.child(branches.len(), counter),
+                                    counter,
+                                )?,
+                            });
+                            next = iff.else_branch;
+                        }
+                        parse::Else::Block(else_token, block) => {
+                            branches.push(Branch {
+                                keywords: quote! { #else_token },
+                                cond: None,
+                                then: create_blocks(
+                                    block.constituents,
+                                    branch_parent.child(branches.len(), counter),
+                                    counter,
+                                )?,
+                            });
+                            break;
+                        }
+                    }
+                }
+
+                blocks.push(Block {
+                    op: Op::Branch(branches),
+                    id: branch_parent.id(),
+                });
+            }
+            Some(parse::Constituent::Match(match_)) => {
+                return Err(syn::Error::new_spanned(
+                    match_.match_token,
+                    "match is not supported yet",
+                ));
+            }
+            Some(parse::Constituent::For(_)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+                let batch_parent = parent.child(blocks.len(), counter);
+
+                let for_ = match peek_ast.next().unwrap() {
+                    parse::Constituent::For(for_) => for_,
+                    _ => panic!(),
+                };
+
+                blocks.push(Block {
+                    op: Op::Batch(Batch {
+                        for_token: for_.for_token,
+                        pat: for_.pat,
+                        expr: for_.expr,
+                        row: create_blocks(
+                            for_.body.constituents,
+                            batch_parent.child(0, counter),
+                            counter,
+                        )?,
+                    }),
+                    id: batch_parent.id(),
+                });
+            }
+            Some(parse::Constituent::InList(..)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+
+                let expr = match peek_ast.next().unwrap() {
+                    parse::Constituent::InList(_, expr) => expr,
+                    _ => panic!(),
+                };
+
+                blocks.push(Block {
+                    op: Op::InList(expr),
+                    id: parent.child(blocks.len(), counter).id(),
+                });
+            }
+            Some(parse::Constituent::Splice(..)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+
+                let expr = match peek_ast.next().unwrap() {
+                    parse::Constituent::Splice(_, expr) => expr,
+                    _ => panic!(),
+                };
+
+                blocks.push(Block {
+                    op: Op::Splice(expr),
+                    id: parent.child(blocks.len(), counter).id(),
+                });
+            }
+            Some(parse::Constituent::OrderBy(_)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+
+                let order_by = match peek_ast.next().unwrap() {
+                    parse::Constituent::OrderBy(order_by) => order_by,
+                    _ => panic!(),
+                };
+
+                blocks.push(Block {
+                    op: Op::OrderBy(order_by),
+                    id: parent.child(blocks.len(), counter).id(),
+                });
+            }
+            Some(parse::Constituent::Group(_)) => {
+                if !pushes.is_empty() {
+                    blocks.push(Block {
+                        op: Op::Push(pushes),
+                        id: parent.child(blocks.len(), counter).id(),
+                    });
+                    pushes = vec![];
+                }
+                let group_parent = parent.child(blocks.len(), counter);
+
+                let group = match peek_ast.next().unwrap() {
+                    parse::Constituent::Group(group) => group,
+                    _ => panic!(),
+                };
+
+                let items = group_items(group.body.constituents)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        create_blocks(item, group_parent.child(index, counter), counter)
+                    })
+                    .collect::<syn::Result<_>>()?;
+
+                blocks.push(Block {
+                    op: Op::Group(Group {
+                        kind: group.kind,
+                        span: group.span,
+                        items,
+                    }),
+                    id: group_parent.id(),
+                });
+            }
+        }
+    }
+}
+
+/// Split the body of a group into its items: control flow, nested groups and
+/// splices are items of their own, and runs of everything else are too.
+fn group_items(constituents: Vec<parse::Constituent>) -> Vec<Vec<parse::Constituent>> {
+    let mut items = vec![];
+    let mut run = vec![];
+    for constituent in constituents {
+        match constituent {
+            parse::Constituent::If(_)
+            | parse::Constituent::Match(_)
+            | parse::Constituent::For(_)
+            | parse::Constituent::Splice(..)
+            | parse::Constituent::Group(_) => {
+                if !run.is_empty() {
+                    items.push(std::mem::take(&mut run));
+                }
+                items.push(vec![constituent]);
+            }
+            _ => run.push(constituent),
+        }
+    }
+    if !run.is_empty() {
+        items.push(run);
+    }
+    items
+}
+
+/// The highest number of bind parameters any path through `blocks` pushes.
+pub fn max_binds(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .map(|block| match &block.op {
+            Op::Push(pushes) => pushes
+                .iter()
+                .filter(|push| matches!(push, Push::Bind(_)))
+                .count(),
+            Op::Branch(branches) => branches
+                .iter()
+                .map(|branch| max_binds(&branch.then))
+                .max()
+                .unwrap_or(0),
+            Op::Group(group) => group
+                .items
+                .iter()
+                .map(|item| max_binds(item))
+                .fold(0, usize::saturating_add),
+            Op::OrderBy(_) => 0,
+            Op::Batch(_) | Op::InList(_) | Op::Splice(_) => usize::MAX,
+        })
+        .fold(0, usize::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use quote::quote;
+
+    #[test]
+    fn conditional() {
+        let foo = Some(1u32);
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT yo FROM stuff "
+            "WHERE "
+            if let Some(bar) = #foo {
+                "id IN (" bar ") "
+            } else {
+                "TRUE "
+            }
+            "AND "
+            "ORDER BY "
+            "date"
+        })
+        .unwrap();
+        let blocks = create_blocks(ast.constituents, Parent::root(), &mut Counter::new()).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(max_binds(&blocks), 1);
+    }
+
+    #[test]
+    fn group_items() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t"
+            where {
+                "a = " a " AND b = " b
+                if let Some(c) = c { "c = " c }
+                "deleted_at IS NULL"
+            }
+        })
+        .unwrap();
+        let blocks = create_blocks(ast.constituents, Parent::root(), &mut Counter::new()).unwrap();
+        assert_eq!(blocks.len(), 2);
+        match &blocks[1].op {
+            Op::Group(group) => assert_eq!(group.items.len(), 3),
+            _ => panic!("expected a group"),
+        }
+        assert_eq!(max_binds(&blocks), 3);
+    }
+
+    #[test]
+    fn splices_are_group_items() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t"
+            where { "deleted_at IS NULL" ..filter "org = " org ..page }
+        })
+        .unwrap();
+        let blocks = create_blocks(ast.constituents, Parent::root(), &mut Counter::new()).unwrap();
+        match &blocks[1].op {
+            Op::Group(group) => assert_eq!(group.items.len(), 4),
+            _ => panic!("expected a group"),
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "INSERT INTO t (a, b) VALUES "
+            for (a, b) in rows {
+                "(" a ", " b ")"
+            }
+            " RETURNING id"
+        })
+        .unwrap();
+        let blocks = create_blocks(ast.constituents, Parent::root(), &mut Counter::new()).unwrap();
+        assert_eq!(blocks.len(), 3);
+        match &blocks[1].op {
+            Op::Batch(batch) => assert_eq!(max_binds(&batch.row), 2),
+            _ => panic!("expected a batch"),
+        }
+    }
+}
@@ -0,0 +1,846 @@
+//! Formatting of `build_query!` and `sql_fragment!` bodies, which rustfmt leaves alone.
+//!
+//! Only whitespace between tokens and the contents of SQL literals change:
+//! binds, conditions, patterns and comments are copied from the source as they
+//! are. Inside literals, whitespace runs collapse to one space and keywords are
+//! uppercased (neither inside quotes), and a literal holding several clauses is
+//! split into one literal per clause. Each clause starts a line, and control
+//! flow is indented by block depth.
+
+use std::ops::Range;
+
+use proc_macro2::{Delimiter, LineColumn, TokenStream, TokenTree};
+use syn::visit::Visit;
+
+use crate::parse;
+
+const INDENT: &str = "    ";
+
+/// Format every `build_query!` and `sql_fragment!` invocation in a Rust source file.
+///
+/// Invocations whose body doesn't parse as `build_query!` syntax are left alone.
+pub fn format_source(source: &str) -> syn::Result<String> {
+    let file = syn::parse_file(source)?;
+    let mut finder = Finder {
+        invocations: vec![],
+    };
+    finder.visit_file(&file);
+
+    let lines = LineIndex::new(source);
+    let mut formatted = source.to_owned();
+    for invocation in finder.invocations.iter().rev() {
+        if syn::parse2::<parse::BuilderAST>(invocation.tokens.clone()).is_err() {
+            continue;
+        }
+        let open = lines.offset(invocation.delimiter.start()) + 1;
+        let close = lines.offset(invocation.delimiter.end()) - 1;
+
+        let items = Lexer {
+            source,
+            lines: &lines,
+        }
+        .items(invocation.tokens.clone(), open..close, false);
+        if items.is_empty() {
+            continue;
+        }
+
+        let line_start = lines.starts[invocation.line - 1];
+        let base: String = source[line_start..]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let mut printer = Printer {
+            lines: vec![],
+            current: String::new(),
+            prefix: format!("{}{}", base, INDENT),
+        };
+        printer.print(&items);
+        printer.finish_line();
+
+        let body = format!("\n{}\n{}", printer.lines.join("\n"), base);
+        formatted.replace_range(open..close, &body);
+    }
+    Ok(formatted)
+}
+
+/// Normalize the contents of one SQL literal, split into one piece per clause.
+///
+/// The pieces concatenate to the normalized SQL. A piece is paired with `true`
+/// when it begins a clause and so should begin a line.
+pub fn format_sql(sql: &str) -> Vec<(String, bool)> {
+    let (normalized, words) = normalize(sql);
+    let starts = clause_starts(&normalized, &words);
+
+    let mut pieces: Vec<(String, bool)> = vec![];
+    let mut piece_start = 0;
+    let mut starts_clause = false;
+    for index in starts {
+        let offset = words[index].offset;
+        if normalized[piece_start..offset].trim().is_empty() {
+            // Only whitespace precedes the clause: keep it with the clause.
+            starts_clause = true;
+            continue;
+        }
+        pieces.push((normalized[piece_start..offset].to_owned(), starts_clause));
+        piece_start = offset;
+        starts_clause = true;
+    }
+    if piece_start < normalized.len() || pieces.is_empty() {
+        pieces.push((normalized[piece_start..].to_owned(), starts_clause));
+    }
+    pieces
+}
+
+struct Word {
+    offset: usize,
+    end: usize,
+    upper: String,
+    depth: usize,
+}
+
+/// Collapse whitespace and uppercase keywords outside quotes and comments,
+/// returning the words of the result with their parenthesis depth.
+fn normalize(sql: &str) -> (String, Vec<Word>) {
+    let mut out = String::with_capacity(sql.len());
+    let mut words = vec![];
+    let mut depth = 0usize;
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                out.push(c);
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                out.push(c);
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if next == '\n' {
+                        break;
+                    }
+                }
+                // The line break ends the comment, so it stands in for the whitespace.
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                out.push(c);
+                out.extend(chars.next());
+                let mut prev = ' ';
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+            }
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                out.push(' ');
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_alphanumeric() || next == '_' || next == '$' {
+                        word.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let upper = word.to_uppercase();
+                let qualified = out.ends_with('.') || chars.peek() == Some(&'.');
+                let offset = out.len();
+                if is_keyword(&upper) && !qualified {
+                    out.push_str(&upper);
+                } else {
+                    out.push_str(&word);
+                }
+                words.push(Word {
+                    offset,
+                    end: out.len(),
+                    upper,
+                    depth,
+                });
+            }
+            '(' => {
+                depth += 1;
+                out.push(c);
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+
+    (out, words)
+}
+
+/// Indices of the words that begin a clause outside parentheses.
+fn clause_starts(sql: &str, words: &[Word]) -> Vec<usize> {
+    let word = |index: usize| words.get(index).map(|word| word.upper.as_str());
+    let adjacent = |a: usize, b: usize| &sql[words[a].end..words[b].offset] == " ";
+
+    let mut starts = vec![];
+    for (index, current) in words.iter().enumerate() {
+        if current.depth > 0 {
+            continue;
+        }
+        let prev = index.checked_sub(1).and_then(word);
+        let next = word(index + 1);
+        let is_start = match current.upper.as_str() {
+            "SELECT" => !matches!(
+                prev,
+                Some("UNION" | "ALL" | "DISTINCT" | "INTERSECT" | "EXCEPT")
+            ),
+            "FROM" => !matches!(prev, Some("DELETE" | "DISTINCT")),
+            "VALUES" => prev != Some("DEFAULT"),
+            "UPDATE" => !matches!(prev, Some("DO" | "FOR")),
+            "WHERE" | "HAVING" | "LIMIT" | "OFFSET" | "RETURNING" | "UNION" | "INTERSECT"
+            | "EXCEPT" | "SET" | "INSERT" | "DELETE" => true,
+            "GROUP" | "ORDER" => next == Some("BY"),
+            "ON" => next == Some("CONFLICT"),
+            "JOIN" => {
+                let mut first = index;
+                while first > 0
+                    && is_join_modifier(&words[first - 1].upper)
+                    && adjacent(first - 1, first)
+                {
+                    first -= 1;
+                }
+                starts.push(first);
+                continue;
+            }
+            _ => false,
+        };
+        if is_start {
+            starts.push(index);
+        }
+    }
+    starts
+}
+
+fn is_join_modifier(word: &str) -> bool {
+    matches!(
+        word,
+        "LEFT" | "RIGHT" | "INNER" | "OUTER" | "FULL" | "CROSS" | "NATURAL"
+    )
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "ALL"
+            | "AND"
+            | "AS"
+            | "ASC"
+            | "BETWEEN"
+            | "BY"
+            | "CASE"
+            | "CONFLICT"
+            | "CROSS"
+            | "DEFAULT"
+            | "DELETE"
+            | "DESC"
+            | "DISTINCT"
+            | "DO"
+            | "ELSE"
+            | "END"
+            | "EXCEPT"
+            | "EXISTS"
+            | "FALSE"
+            | "FIRST"
+            | "FROM"
+            | "FULL"
+            | "GROUP"
+            | "HAVING"
+            | "ILIKE"
+            | "IN"
+            | "INNER"
+            | "INSERT"
+            | "INTERSECT"
+            | "INTO"
+            | "IS"
+            | "JOIN"
+            | "LAST"
+            | "LEFT"
+            | "LIKE"
+            | "LIMIT"
+            | "NATURAL"
+            | "NOT"
+            | "NOTHING"
+            | "NULL"
+            | "NULLS"
+            | "OFFSET"
+            | "ON"
+            | "OR"
+            | "ORDER"
+            | "OUTER"
+            | "RETURNING"
+            | "RIGHT"
+            | "SELECT"
+            | "SET"
+            | "THEN"
+            | "TRUE"
+            | "UNION"
+            | "UPDATE"
+            | "USING"
+            | "VALUES"
+            | "WHEN"
+            | "WHERE"
+            | "WITH"
+    )
+}
+
+struct Invocation {
+    line: usize,
+    delimiter: proc_macro2::Span,
+    tokens: TokenStream,
+}
+
+struct Finder {
+    invocations: Vec<Invocation>,
+}
+
+impl<'ast> Visit<'ast> for Finder {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let is_builder_macro = mac.path.segments.last().is_some_and(|segment| {
+            segment.ident == "build_query" || segment.ident == "sql_fragment"
+        });
+        if !is_builder_macro {
+            return;
+        }
+        let delimiter = match &mac.delimiter {
+            syn::MacroDelimiter::Paren(token) => token.span,
+            syn::MacroDelimiter::Brace(token) => token.span,
+            syn::MacroDelimiter::Bracket(token) => token.span,
+        };
+        self.invocations.push(Invocation {
+            line: mac.path.segments[0].ident.span().start().line,
+            delimiter,
+            tokens: mac.tokens.clone(),
+        });
+    }
+}
+
+/// Byte offsets of the starts of lines, to turn span locations into offsets.
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        LineIndex { source, starts }
+    }
+
+    fn offset(&self, location: LineColumn) -> usize {
+        let start = self.starts[location.line - 1];
+        self.source[start..]
+            .char_indices()
+            .nth(location.column)
+            .map_or(self.source.len(), |(index, _)| start + index)
+    }
+}
+
+enum Item {
+    /// An inner attribute, copied as it is.
+    Attr(String),
+    Sql {
+        value: String,
+        raw: bool,
+    },
+    /// Binds, `in` lists and anything else that isn't SQL or a block.
+    Code(String),
+    /// `order by` and its terms, on a line of their own.
+    Clause(String),
+    /// A braced block with the code before it, like `if cond` or `Some(x) =>`.
+    Block {
+        header: String,
+        body: Vec<Item>,
+    },
+    Comment {
+        text: String,
+        own_line: bool,
+        line: bool,
+    },
+}
+
+struct Run {
+    range: Range<usize>,
+    first: String,
+    tokens: usize,
+    /// Whether this is an `order by`.
+    clause: bool,
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    lines: &'a LineIndex<'a>,
+}
+
+impl Lexer<'_> {
+    fn range(&self, token: &TokenTree) -> Range<usize> {
+        match token {
+            TokenTree::Group(group) => {
+                self.lines.offset(group.span_open().start())
+                    ..self.lines.offset(group.span_close().end())
+            }
+            _ => self.lines.offset(token.span().start())..self.lines.offset(token.span().end()),
+        }
+    }
+
+    /// The items of a token stream spanning `range` of the source. Match arms
+    /// are lexed with `arms`, which keeps guards in the arm's header.
+    fn items(&self, stream: TokenStream, range: Range<usize>, arms: bool) -> Vec<Item> {
+        let tokens: Vec<TokenTree> = stream.into_iter().collect();
+        let mut items = vec![];
+        let mut run: Option<Run> = None;
+        let mut last_end = range.start;
+        let mut index = 0;
+
+        while index < tokens.len() {
+            let token = &tokens[index];
+            let token_range = self.range(token);
+
+            if is_inner_attr(&tokens[index..]) {
+                self.flush(&mut run, &mut items);
+                self.comments(last_end..token_range.start, &mut items);
+                let end = self.range(&tokens[index + 2]).end;
+                items.push(Item::Attr(self.source[token_range.start..end].to_owned()));
+                last_end = end;
+                index += 3;
+                continue;
+            }
+
+            match token {
+                TokenTree::Literal(literal) => match syn::Lit::new(literal.clone()) {
+                    syn::Lit::Str(lit) => {
+                        self.flush(&mut run, &mut items);
+                        self.comments(last_end..token_range.start, &mut items);
+                        items.push(Item::Sql {
+                            value: lit.value(),
+                            raw: literal.to_string().starts_with('r'),
+                        });
+                    }
+                    _ => self.extend(&mut run, &mut items, last_end, token, token_range.clone()),
+                },
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                    let header = run
+                        .take()
+                        .map_or_else(String::new, |run| self.source[run.range].to_owned());
+                    self.comments(last_end..token_range.start, &mut items);
+                    let inner = token_range.start + 1..token_range.end - 1;
+                    let body = self.items(group.stream(), inner, header.starts_with("match"));
+                    items.push(Item::Block { header, body });
+                }
+                TokenTree::Ident(ident) if !arms => {
+                    let name = ident.to_string();
+                    let continues = match &run {
+                        Some(run) => {
+                            (name == "if" && run.first == "else" && run.tokens == 1)
+                                || (name == "in" && run.first == "for")
+                        }
+                        None => true,
+                    };
+                    let order_by = name == "by"
+                        && run
+                            .as_ref()
+                            .is_some_and(|run| run.first == "order" && run.tokens == 1);
+                    let starts = matches!(
+                        name.as_str(),
+                        "if" | "else" | "match" | "for" | "in" | "where" | "or" | "set"
+                    ) || (name == "order"
+                        && matches!(tokens.get(index + 1), Some(TokenTree::Ident(by)) if by == "by"));
+                    if starts && !continues {
+                        self.flush(&mut run, &mut items);
+                    }
+                    self.extend(&mut run, &mut items, last_end, token, token_range.clone());
+                    if let (true, Some(run)) = (order_by, &mut run) {
+                        run.clause = true;
+                    }
+                }
+                _ => self.extend(&mut run, &mut items, last_end, token, token_range.clone()),
+            }
+
+            last_end = token_range.end;
+            index += 1;
+        }
+
+        self.flush(&mut run, &mut items);
+        self.comments(last_end..range.end, &mut items);
+        items
+    }
+
+    fn extend(
+        &self,
+        run: &mut Option<Run>,
+        items: &mut Vec<Item>,
+        last_end: usize,
+        token: &TokenTree,
+        range: Range<usize>,
+    ) {
+        match run {
+            Some(run) => {
+                run.range.end = range.end;
+                run.tokens += 1;
+            }
+            None => {
+                self.comments(last_end..range.start, items);
+                *run = Some(Run {
+                    range,
+                    first: token.to_string(),
+                    tokens: 1,
+                    clause: false,
+                });
+            }
+        }
+    }
+
+    fn flush(&self, run: &mut Option<Run>, items: &mut Vec<Item>) {
+        if let Some(run) = run.take() {
+            let code = self.source[run.range].to_owned();
+            items.push(if run.clause {
+                Item::Clause(code)
+            } else {
+                Item::Code(code)
+            });
+        }
+    }
+
+    /// The comments in a stretch of source between tokens.
+    fn comments(&self, range: Range<usize>, items: &mut Vec<Item>) {
+        let gap = &self.source[range];
+        let mut own_line = items.is_empty();
+        let mut rest = gap;
+
+        while let Some(start) = rest.find('/') {
+            own_line |= rest[..start].contains('\n');
+            let comment = &rest[start..];
+            let len = if comment.starts_with("//") {
+                comment.find('\n').unwrap_or(comment.len())
+            } else if comment.starts_with("/*") {
+                block_comment_len(comment)
+            } else {
+                break;
+            };
+            items.push(Item::Comment {
+                text: comment[..len].trim_end().to_owned(),
+                own_line,
+                line: comment.starts_with("//"),
+            });
+            own_line = false;
+            rest = &comment[len..];
+        }
+    }
+}
+
+fn is_inner_attr(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [TokenTree::Punct(hash), TokenTree::Punct(bang), TokenTree::Group(group), ..] => {
+            hash.as_char() == '#'
+                && bang.as_char() == '!'
+                && group.delimiter() == Delimiter::Bracket
+        }
+        _ => false,
+    }
+}
+
+/// The length of the (possibly nested) block comment `comment` starts with.
+fn block_comment_len(comment: &str) -> usize {
+    let bytes = comment.as_bytes();
+    let mut depth = 0;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        match &bytes[index..index + 2] {
+            b"/*" => {
+                depth += 1;
+                index += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    comment.len()
+}
+
+struct Printer {
+    lines: Vec<String>,
+    current: String,
+    prefix: String,
+}
+
+impl Printer {
+    fn inline(&mut self, text: &str) {
+        if self.current.is_empty() {
+            self.current.push_str(&self.prefix);
+        } else {
+            self.current.push(' ');
+        }
+        self.current.push_str(text);
+    }
+
+    fn finish_line(&mut self) {
+        if !self.current.is_empty() {
+            self.lines.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    fn print(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Attr(text) => {
+                    self.finish_line();
+                    self.inline(text);
+                    self.finish_line();
+                }
+                Item::Sql { value, raw } => {
+                    for (piece, starts_clause) in format_sql(value) {
+                        if starts_clause {
+                            self.finish_line();
+                        }
+                        self.inline(&quote(&piece, *raw));
+                    }
+                }
+                Item::Code(text) => self.inline(text),
+                Item::Clause(text) => {
+                    self.finish_line();
+                    self.inline(text);
+                    self.finish_line();
+                }
+                Item::Comment {
+                    text,
+                    own_line,
+                    line,
+                } => {
+                    if *own_line {
+                        self.finish_line();
+                    }
+                    self.inline(text);
+                    if *own_line || *line {
+                        self.finish_line();
+                    }
+                }
+                Item::Block { header, body } => {
+                    let closing = format!("{}}}", self.prefix);
+                    if header.starts_with("else")
+                        && self.current.is_empty()
+                        && self.lines.last() == Some(&closing)
+                    {
+                        self.current = self.lines.pop().unwrap();
+                        self.current.push(' ');
+                        self.current.push_str(header);
+                    } else {
+                        self.finish_line();
+                        if !header.is_empty() {
+                            self.inline(header);
+                        }
+                    }
+
+                    if body.is_empty() {
+                        self.inline("{}");
+                        self.finish_line();
+                        continue;
+                    }
+                    self.inline("{");
+                    self.finish_line();
+                    self.prefix.push_str(INDENT);
+                    self.print(body);
+                    self.finish_line();
+                    self.prefix.truncate(self.prefix.len() - INDENT.len());
+                    self.inline("}");
+                    self.finish_line();
+                }
+            }
+        }
+    }
+}
+
+fn quote(sql: &str, raw: bool) -> String {
+    if !raw || !sql.contains('"') {
+        return format!("{:?}", sql);
+    }
+    let mut hashes = String::from("#");
+    while sql.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}\"{}\"{}", hashes, sql, hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_sql(sql: &str, expected: &[(&str, bool)]) {
+        let expected: Vec<(String, bool)> = expected
+            .iter()
+            .map(|(piece, starts_clause)| (piece.to_string(), *starts_clause))
+            .collect();
+        assert_eq!(format_sql(sql), expected);
+    }
+
+    #[test]
+    fn normalizes_literals() {
+        check_sql(
+            "select *\n   from t where name = 'select  x' and \"from\" = t.order ",
+            &[
+                ("SELECT * ", true),
+                ("FROM t ", true),
+                ("WHERE name = 'select  x' AND \"from\" = t.order ", true),
+            ],
+        );
+        check_sql(
+            " left outer join u on u.id = t.u_id -- note\n  order by id",
+            &[
+                (" LEFT OUTER JOIN u ON u.id = t.u_id -- note\n", true),
+                ("ORDER BY id", true),
+            ],
+        );
+        check_sql("a = ", &[("a = ", false)]);
+        check_sql(
+            "id in (select id from u) ",
+            &[("id IN (SELECT id FROM u) ", false)],
+        );
+        check_sql(
+            "delete from t where x is distinct from y",
+            &[
+                ("DELETE FROM t ", true),
+                ("WHERE x IS DISTINCT FROM y", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn formats_invocations() {
+        let source = r#"
+fn find(id: Option<i32>) -> Query {
+    let query = build_query!(#![dialect(Postgres)]
+        "select * from t where "  if let Some(id) = id { "id = " id    // by id
+    } else {
+    /* everything */ "true" }
+          " order   by name" match order { Order::Asc => { " asc" } Order::Desc => {" desc"} }
+    );
+    query
+}
+"#;
+        let expected = r#"
+fn find(id: Option<i32>) -> Query {
+    let query = build_query!(
+        #![dialect(Postgres)]
+        "SELECT * "
+        "FROM t "
+        "WHERE "
+        if let Some(id) = id {
+            "id = " id // by id
+        } else {
+            /* everything */
+            "TRUE"
+        }
+        " ORDER BY name"
+        match order {
+            Order::Asc => {
+                " ASC"
+            }
+            Order::Desc => {
+                " DESC"
+            }
+        }
+    );
+    query
+}
+"#;
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn keeps_binds_and_loops() {
+        let source = "fn f() { build_query!(\"INSERT INTO t (a, b) VALUES \" for (a, b) in rows { \"(\" a \", \" b + 1 \")\" } \" RETURNING id\") }\n";
+        let expected = r#"fn f() { build_query!(
+    "INSERT INTO t (a, b) "
+    "VALUES "
+    for (a, b) in rows {
+        "(" a ", " b + 1 ")"
+    }
+    " RETURNING id"
+) }
+"#;
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn formats_fragments_and_keeps_splices() {
+        let source = "fn f() { sql_fragment!(\"age >=\" a); build_query!(\"select * from t where \" ..filter) }\n";
+        let expected = r#"fn f() { sql_fragment!(
+    "age >=" a
+); build_query!(
+    "SELECT * "
+    "FROM t "
+    "WHERE " ..filter
+) }
+"#;
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn formats_groups() {
+        let source = "fn f() { build_query!(\"select * from t\" where { \"a = \" a or { if b { \"b\" } \"c\" } } \" order by a\") }\n";
+        let expected = r#"fn f() { build_query!(
+    "SELECT * "
+    "FROM t"
+    where {
+        "a = " a
+        or {
+            if b {
+                "b"
+            }
+            "c"
+        }
+    }
+    " ORDER BY a"
+) }
+"#;
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_order_by_terms() {
+        let source = "fn f() { build_query!(\"select * from t\" order by sort.desc(), Column::Id \" limit 10\") }\n";
+        let expected = r#"fn f() { build_query!(
+    "SELECT * "
+    "FROM t"
+    order by sort.desc(), Column::Id
+    " LIMIT 10"
+) }
+"#;
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn skips_other_macros() {
+        let source = "fn f() { other!(\"select  1\"); build_query!(if) }\n";
+        assert_eq!(format_source(source).unwrap(), source);
+    }
+}
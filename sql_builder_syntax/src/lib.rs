@@ -0,0 +1,7 @@
+//! The `build_query!` syntax tree and its analysis, shared by the macros and tooling.
+
+pub mod blocks;
+pub mod format;
+pub mod parse;
+pub mod scan;
+pub mod variants;
@@ -0,0 +1,632 @@
+use syn::parse::{Parse, ParseStream};
+
+pub struct SqlBlock {
+    pub brace_token: syn::token::Brace,
+    pub constituents: Vec<Constituent>,
+}
+
+impl Parse for SqlBlock {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        Ok(SqlBlock {
+            brace_token: syn::braced!(content in input),
+            constituents: parse_constituents(&content)?,
+        })
+    }
+}
+
+pub struct If {
+    pub if_token: syn::Token![if],
+    pub cond: Box<syn::Expr>,
+    pub then_branch: SqlBlock,
+    pub else_branch: Option<Else>,
+}
+
+impl Parse for If {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(If {
+            if_token: input.parse()?,
+            cond: Box::new(syn::Expr::parse_without_eager_brace(input)?),
+            then_branch: input.parse()?,
+            else_branch: {
+                if input.peek(syn::Token![else]) {
+                    Some(input.parse()?)
+                } else {
+                    None
+                }
+            },
+        })
+    }
+}
+
+pub enum Else {
+    If(syn::Token![else], Box<If>),
+    Block(syn::Token![else], SqlBlock),
+}
+
+impl Parse for Else {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let else_token: syn::Token![else] = input.parse()?;
+        let lookahead = input.lookahead1();
+
+        if input.peek(syn::Token![if]) {
+            Ok(Else::If(else_token, Box::new(input.parse()?)))
+        } else if input.peek(syn::token::Brace) {
+            Ok(Else::Block(else_token, input.parse()?))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+pub struct Match {
+    pub match_token: syn::Token![match],
+    pub expr: Box<syn::Expr>,
+    pub brace_token: syn::token::Brace,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Parse for Match {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let match_token: syn::Token![match] = input.parse()?;
+        let expr = syn::Expr::parse_without_eager_brace(input)?;
+
+        let content;
+        let brace_token = syn::braced!(content in input);
+
+        let mut arms = Vec::new();
+        while !content.is_empty() {
+            arms.push(content.call(MatchArm::parse)?);
+        }
+
+        Ok(Match {
+            match_token,
+            expr: Box::new(expr),
+            brace_token,
+            arms,
+        })
+    }
+}
+
+pub struct MatchArm {
+    pub pat: syn::Pat,
+    pub guard: Option<(syn::Token![if], Box<syn::Expr>)>,
+    pub fat_arrow_token: syn::Token![=>],
+    pub body: SqlBlock,
+}
+
+impl Parse for MatchArm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(MatchArm {
+            pat: {
+                let leading_vert: Option<syn::Token![|]> = input.parse()?;
+                let pat: syn::Pat = input.parse()?;
+                if leading_vert.is_some() || input.peek(syn::Token![|]) {
+                    let mut cases = syn::punctuated::Punctuated::new();
+                    cases.push_value(pat);
+                    while input.peek(syn::Token![|]) {
+                        let punct = input.parse()?;
+                        cases.push_punct(punct);
+                        let pat: syn::Pat = input.parse()?;
+                        cases.push_value(pat);
+                    }
+                    syn::Pat::Or(syn::PatOr {
+                        attrs: Vec::new(),
+                        leading_vert,
+                        cases,
+                    })
+                } else {
+                    pat
+                }
+            },
+            guard: {
+                if input.peek(syn::Token![if]) {
+                    let if_token: syn::Token![if] = input.parse()?;
+                    let guard: syn::Expr = input.parse()?;
+                    Some((if_token, Box::new(guard)))
+                } else {
+                    None
+                }
+            },
+            fat_arrow_token: input.parse()?,
+            body: input.parse()?,
+        })
+    }
+}
+
+/// A batch of rows: the body is rendered once per item of `expr`, comma separated.
+pub struct For {
+    pub for_token: syn::Token![for],
+    pub pat: syn::Pat,
+    pub in_token: syn::Token![in],
+    pub expr: Box<syn::Expr>,
+    pub body: SqlBlock,
+}
+
+impl Parse for For {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(For {
+            for_token: input.parse()?,
+            pat: input.parse()?,
+            in_token: input.parse()?,
+            expr: Box::new(syn::Expr::parse_without_eager_brace(input)?),
+            body: input.parse()?,
+        })
+    }
+}
+
+/// How deeply groups can nest, as `MAX_GROUP_DEPTH` in the runtime, which a test
+/// of the runtime checks along with the rendering of each `GroupKind`.
+pub const MAX_GROUP_DEPTH: usize = 8;
+
+/// The kinds of `group { ... }`, whose items are joined depending on which of
+/// them render anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    /// `where { ... }`: ` WHERE a AND b`, or nothing.
+    Where,
+    /// `or { ... }`: `(a OR b)`, or nothing.
+    Or,
+    /// `set { ... }`: ` SET a, b`; a build error when empty.
+    Set,
+}
+
+impl GroupKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            GroupKind::Where => "where",
+            GroupKind::Or => "or",
+            GroupKind::Set => "set",
+        }
+    }
+
+    pub fn open(self) -> &'static str {
+        match self {
+            GroupKind::Where => " WHERE ",
+            GroupKind::Or => "(",
+            GroupKind::Set => " SET ",
+        }
+    }
+
+    pub fn separator(self) -> &'static str {
+        match self {
+            GroupKind::Where => " AND ",
+            GroupKind::Or => " OR ",
+            GroupKind::Set => ", ",
+        }
+    }
+
+    pub fn close(self) -> &'static str {
+        match self {
+            GroupKind::Where => "",
+            GroupKind::Or => ")",
+            GroupKind::Set => "",
+        }
+    }
+}
+
+/// A group of items: each `if` (or `match`, nested group or `..` splice) is an
+/// item, and so is each run of literals, binds and IN lists between them.
+pub struct Group {
+    pub kind: GroupKind,
+    pub span: proc_macro2::Span,
+    pub body: SqlBlock,
+}
+
+impl Group {
+    fn peek(input: ParseStream) -> Option<GroupKind> {
+        if !input.peek2(syn::token::Brace) {
+            return None;
+        }
+        if input.peek(syn::Token![where]) {
+            return Some(GroupKind::Where);
+        }
+        if input.peek(syn::Ident) {
+            let ident = input.fork().parse::<proc_macro2::Ident>().ok()?;
+            if ident == "or" {
+                return Some(GroupKind::Or);
+            }
+            if ident == "set" {
+                return Some(GroupKind::Set);
+            }
+        }
+        None
+    }
+
+    fn parse(kind: GroupKind, input: ParseStream) -> syn::Result<Self> {
+        let span = match kind {
+            GroupKind::Where => input.parse::<syn::Token![where]>()?.span,
+            GroupKind::Or | GroupKind::Set => input.parse::<proc_macro2::Ident>()?.span(),
+        };
+        Ok(Group {
+            kind,
+            span,
+            body: input.parse()?,
+        })
+    }
+}
+
+pub enum Constituent {
+    Literal(syn::LitStr),
+    Bind(syn::Expr),
+    Block(SqlBlock),
+    If(If),
+    Match(Match),
+    For(For),
+    /// `in expr`: a parenthesized placeholder list binding each item of `expr`.
+    InList(syn::Token![in], Box<syn::Expr>),
+    /// `..expr`: an already built `Query` spliced in, its placeholders renumbered.
+    Splice(syn::Token![..], Box<syn::Expr>),
+    Group(Group),
+    OrderBy(OrderBy),
+}
+
+pub struct BuilderAST {
+    /// Inner attributes (`#![...]`) configuring the generated builder.
+    pub attrs: Vec<syn::Attribute>,
+    pub constituents: Vec<Constituent>,
+}
+
+/// `order by term, ...`: ` ORDER BY` and the column, direction and NULLS order
+/// of each term, which is a value implementing the runtime `OrderBy` trait.
+pub struct OrderBy {
+    pub span: proc_macro2::Span,
+    pub terms: syn::punctuated::Punctuated<syn::Expr, syn::Token![,]>,
+}
+
+impl OrderBy {
+    fn peek(input: ParseStream) -> bool {
+        let fork = input.fork();
+        ["order", "by"].iter().all(|keyword| {
+            fork.parse::<proc_macro2::Ident>()
+                .is_ok_and(|ident| ident == keyword)
+        })
+    }
+}
+
+impl Parse for OrderBy {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.parse::<proc_macro2::Ident>()?.span();
+        input.parse::<proc_macro2::Ident>()?;
+        let mut terms = syn::punctuated::Punctuated::new();
+        loop {
+            terms.push_value(input.parse()?);
+            if !input.peek(syn::Token![,]) {
+                return Ok(OrderBy { span, terms });
+            }
+            terms.push_punct(input.parse()?);
+        }
+    }
+}
+
+fn parse_next_constituent(input: ParseStream) -> syn::Result<Constituent> {
+    if input.peek(syn::LitStr) {
+        return Ok(Constituent::Literal(input.parse()?));
+    }
+
+    if input.peek(syn::token::Brace) {
+        return Ok(Constituent::Block(input.parse()?));
+    }
+
+    if input.peek(syn::Token!(if)) {
+        return Ok(Constituent::If(input.parse()?));
+    }
+
+    if input.peek(syn::Token!(match)) {
+        return Ok(Constituent::Match(input.parse()?));
+    }
+
+    if input.peek(syn::Token!(for)) {
+        return Ok(Constituent::For(input.parse()?));
+    }
+
+    if input.peek(syn::Token!(in)) {
+        let in_token = input.parse()?;
+        let expr = input.parse()?;
+        return Ok(Constituent::InList(in_token, Box::new(expr)));
+    }
+
+    if let Some(kind) = Group::peek(input) {
+        return Ok(Constituent::Group(Group::parse(kind, input)?));
+    }
+
+    if OrderBy::peek(input) {
+        return Ok(Constituent::OrderBy(input.parse()?));
+    }
+
+    if input.peek(syn::Token!(..)) {
+        let dot2_token = input.parse()?;
+        let expr = input.parse()?;
+        return Ok(Constituent::Splice(dot2_token, Box::new(expr)));
+    }
+
+    let expr = input.parse::<syn::Expr>()?;
+
+    Ok(Constituent::Bind(expr))
+}
+
+/// `a ..b` parses as a range, which can't be bound: it is `a` followed by the
+/// splice `..b`. IN lists are left alone, since they can iterate a range.
+fn split_splice(expr: syn::Expr) -> (syn::Expr, Option<Constituent>) {
+    match expr {
+        syn::Expr::Range(syn::ExprRange {
+            attrs,
+            from: Some(from),
+            limits: syn::RangeLimits::HalfOpen(dot2_token),
+            to: Some(to),
+        }) if attrs.is_empty() => (*from, Some(Constituent::Splice(dot2_token, to))),
+        expr => (expr, None),
+    }
+}
+
+fn parse_constituents(input: ParseStream) -> syn::Result<Vec<Constituent>> {
+    let mut constituents = vec![];
+    loop {
+        if input.is_empty() {
+            return Ok(constituents);
+        }
+
+        let (constituent, splice) = match parse_next_constituent(input)? {
+            Constituent::Bind(expr) => {
+                let (expr, splice) = split_splice(expr);
+                (Constituent::Bind(expr), splice)
+            }
+            Constituent::Splice(dot2_token, expr) => {
+                let (expr, splice) = split_splice(*expr);
+                (Constituent::Splice(dot2_token, Box::new(expr)), splice)
+            }
+            constituent => (constituent, None),
+        };
+        constituents.push(constituent);
+        constituents.extend(splice);
+    }
+}
+
+impl Parse for BuilderAST {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_inner)?;
+        let constituents = parse_constituents(input)?;
+        Ok(BuilderAST {
+            attrs,
+            constituents,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use quote::quote;
+
+    #[test]
+    fn parse_sql_block() {
+        let ast: SqlBlock = syn::parse2(quote! {
+            { "SELECT" }
+        })
+        .unwrap();
+        assert_eq!(ast.constituents.len(), 1);
+    }
+
+    #[test]
+    fn parse_if() {
+        syn::parse2::<If>(quote! {
+            if 2 { "SELECT" }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_else() {
+        syn::parse2::<Else>(quote! {
+            else if 2 { "A" "B" }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_if_else() {
+        syn::parse2::<If>(quote! {
+            if 2 { "A" } else { "B" }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_if_else_if() {
+        syn::parse2::<If>(quote! {
+            if 2 { "A" "B" } else if 3 { "C" "D" }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_match_arm() {
+        syn::parse2::<MatchArm>(quote! {
+            Some(2) => {}
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_match_empty() {
+        syn::parse2::<Match>(quote! {
+            match Some(42) {}
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_match_var_with_arms() {
+        let var = quote::format_ident!("var");
+        let match_ = syn::parse2::<Match>(quote! {
+            match #var {
+                Some(37) => { "A" "B" }
+                Some(83) => { "A" "C" }
+            }
+        })
+        .unwrap();
+
+        assert_eq!(match_.arms.len(), 2);
+    }
+
+    #[test]
+    fn parse_groups() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t"
+            where {
+                "a = " a
+                or { if b { "b" } "c" }
+            }
+        })
+        .unwrap();
+        match &ast.constituents[1] {
+            Constituent::Group(group) => {
+                assert_eq!(group.kind, GroupKind::Where);
+                assert_eq!(group.body.constituents.len(), 3);
+                assert!(matches!(
+                    &group.body.constituents[2],
+                    Constituent::Group(Group {
+                        kind: GroupKind::Or,
+                        ..
+                    })
+                ));
+            }
+            _ => panic!("expected a group"),
+        }
+    }
+
+    #[test]
+    fn parse_splice() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t WHERE id IN (" ..sub ") AND a = " a
+        })
+        .unwrap();
+        assert!(matches!(ast.constituents[1], Constituent::Splice(..)));
+        assert!(matches!(ast.constituents[3], Constituent::Bind(_)));
+    }
+
+    #[test]
+    fn parse_order_by() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t" order by sort.desc(), Column::Id " LIMIT " limit
+        })
+        .unwrap();
+        match &ast.constituents[1] {
+            Constituent::OrderBy(order_by) => assert_eq!(order_by.terms.len(), 2),
+            _ => panic!("expected order by"),
+        }
+        assert!(matches!(ast.constituents[2], Constituent::Literal(_)));
+        assert_eq!(ast.constituents.len(), 4);
+    }
+
+    #[test]
+    fn parse_for() {
+        let rows = quote::format_ident!("rows");
+        let for_ = syn::parse2::<For>(quote! {
+            for (a, b) in #rows.iter() { "(" a ", " b ")" }
+        })
+        .unwrap();
+
+        assert_eq!(for_.body.constituents.len(), 5);
+    }
+
+    #[test]
+    fn parse_ast_with_block() {
+        syn::parse2::<BuilderAST>(quote! {
+            "SELECT col FROM table "
+            "WHERE " {
+                "LOLG"
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_ast_with_try_block() {
+        let a = Some(42_i32);
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT * " { "WHERE " #a? }
+        })
+        .unwrap();
+        assert_eq!(ast.constituents.len(), 2);
+    }
+
+    #[test]
+    fn parse_ast_inner_attrs() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            #![sink(&mut buf)]
+            "SELECT 1"
+        })
+        .unwrap();
+        assert_eq!(ast.attrs.len(), 1);
+        assert_eq!(ast.constituents.len(), 1);
+    }
+
+    #[test]
+    fn parse_ast_bind() {
+        let two = 2i32;
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT col FROM table WHERE a = " #two + #two " AND b IS NOT NULL"
+        })
+        .unwrap();
+
+        assert_eq!(ast.constituents.len(), 3);
+    }
+
+    #[test]
+    fn parse_ast_in_list() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t WHERE id IN " in ids.iter() " AND TRUE"
+        })
+        .unwrap();
+
+        assert_eq!(ast.constituents.len(), 3);
+    }
+
+    #[test]
+    fn parse_ast_splice_after_bind() {
+        let ast: BuilderAST = syn::parse2(quote! {
+            "a = " a ..filter "b IN " in 0..n ..page.predicate()
+        })
+        .unwrap();
+
+        assert!(matches!(
+            &ast.constituents[..],
+            [
+                Constituent::Literal(_),
+                Constituent::Bind(syn::Expr::Path(_)),
+                Constituent::Splice(..),
+                Constituent::Literal(_),
+                Constituent::InList(..),
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_ast_if() {
+        let test = true;
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT yo FROM saft WHERE"
+            if #test { "bar = " "yo" } else { "TRUE" }
+            "YO"
+        })
+        .unwrap();
+
+        assert_eq!(ast.constituents.len(), 3);
+    }
+
+    #[test]
+    fn parse_ast_match() {
+        let test = Some(42);
+        let ast: BuilderAST = syn::parse2(quote! {
+            "SELECT yo FROM saft WHERE"
+            match #test {
+                Some(42) => { "TRUE" }
+            }
+        })
+        .unwrap();
+
+        assert_eq!(ast.constituents.len(), 2);
+    }
+}
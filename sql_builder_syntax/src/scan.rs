@@ -0,0 +1,405 @@
+//! Finding `build_query!` invocations in source files without compiling them.
+
+use std::path::{Path, PathBuf};
+
+use syn::visit::Visit;
+
+use crate::{blocks, parse, variants};
+
+/// A `build_query!` invocation found in the sources.
+pub struct Invocation {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// The innermost function or method the invocation is in.
+    pub function: Option<String>,
+    /// The name of the dialect given with `#![dialect(...)]`, if any.
+    pub dialect: Option<String>,
+    pub style: variants::PlaceholderStyle,
+    pub variants: Vec<variants::Variant>,
+    pub truncated: bool,
+}
+
+impl Invocation {
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+/// Where a `build_query!` invocation is reported to be, as a line and 1-based
+/// column: the first token inside it, or `None` if it is empty. The macro sees
+/// no further out, so the CLI, the manifest, fragment origins and tracing all
+/// locate invocations by this.
+pub fn call_site(tokens: &proc_macro2::TokenStream) -> Option<(usize, usize)> {
+    let start = tokens.clone().into_iter().next()?.span().start();
+    Some((start.line, start.column + 1))
+}
+
+/// A file or invocation that couldn't be read or parsed.
+pub struct ScanError {
+    pub location: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Scan {
+    pub invocations: Vec<Invocation>,
+    pub errors: Vec<ScanError>,
+}
+
+/// Find every `build_query!` invocation in the `.rs` files under `paths`, skipping
+/// `target` directories and hidden ones.
+pub fn scan(paths: &[PathBuf]) -> Scan {
+    let mut scan = Scan::default();
+    for file in files(paths) {
+        scan_file(&file, &mut scan);
+    }
+    scan
+}
+
+/// The `.rs` files under `paths`, skipping `target` directories and hidden ones.
+pub fn files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        collect_files(path, &mut files);
+    }
+    files.sort();
+    files
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path.to_owned());
+        }
+        return;
+    }
+
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() && (name == "target" || name.starts_with('.')) {
+            continue;
+        }
+        collect_files(&path, files);
+    }
+}
+
+fn scan_file(file: &Path, scan: &mut Scan) {
+    let error = |message: String| ScanError {
+        location: file.display().to_string(),
+        message,
+    };
+
+    let source = match std::fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(err) => return scan.errors.push(error(err.to_string())),
+    };
+    let syntax = match syn::parse_file(&source) {
+        Ok(syntax) => syntax,
+        Err(err) => return scan.errors.push(error(err.to_string())),
+    };
+
+    let mut visitor = MacroVisitor {
+        file,
+        function: None,
+        scan,
+    };
+    visitor.visit_file(&syntax);
+}
+
+struct MacroVisitor<'a> {
+    file: &'a Path,
+    function: Option<String>,
+    scan: &'a mut Scan,
+}
+
+impl MacroVisitor<'_> {
+    fn in_function(&mut self, ident: &syn::Ident, visit: impl FnOnce(&mut Self)) {
+        let outer = self.function.replace(ident.to_string());
+        visit(self);
+        self.function = outer;
+    }
+}
+
+impl MacroVisitor<'_> {
+    /// Look for invocations in the body of another macro, such as `assert_eq!` or
+    /// `vec!`, which `syn` leaves as tokens. Bodies that aren't expressions or
+    /// statements but mention `build_query` are reported as not scanned.
+    fn visit_macro_body(&mut self, mac: &syn::Macro) {
+        if !mentions_build_query(mac.tokens.clone()) {
+            return;
+        }
+        match mac.parse_body_with(parse_macro_body) {
+            Ok(stmts) => {
+                for stmt in &stmts {
+                    self.visit_stmt(stmt);
+                }
+            }
+            Err(_) => {
+                let start = mac.path.segments[0].ident.span().start();
+                self.scan.errors.push(ScanError {
+                    location: format!(
+                        "{}:{}:{}",
+                        self.file.display(),
+                        start.line,
+                        start.column + 1
+                    ),
+                    message: "build_query! in a macro body that couldn't be parsed, not scanned"
+                        .to_owned(),
+                });
+            }
+        }
+    }
+}
+
+fn mentions_build_query(tokens: proc_macro2::TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => ident == "build_query",
+        proc_macro2::TokenTree::Group(group) => mentions_build_query(group.stream()),
+        _ => false,
+    })
+}
+
+/// Expressions separated by `,` or `;`, as in `assert_eq!(a, b)` or `vec![a; n]`,
+/// or else statements, as in a block.
+fn parse_macro_body(input: syn::parse::ParseStream) -> syn::Result<Vec<syn::Stmt>> {
+    use syn::parse::discouraged::Speculative;
+
+    let fork = input.fork();
+    match parse_exprs(&fork) {
+        Ok(exprs) => {
+            input.advance_to(&fork);
+            Ok(exprs)
+        }
+        Err(_) => syn::Block::parse_within(input),
+    }
+}
+
+fn parse_exprs(input: syn::parse::ParseStream) -> syn::Result<Vec<syn::Stmt>> {
+    let mut exprs = vec![];
+    while !input.is_empty() {
+        exprs.push(syn::Stmt::Expr(input.parse()?));
+        if input.is_empty() {
+            break;
+        }
+        if input.peek(syn::Token![;]) {
+            input.parse::<syn::Token![;]>()?;
+        } else {
+            input.parse::<syn::Token![,]>()?;
+        }
+    }
+    Ok(exprs)
+}
+
+impl<'ast> Visit<'ast> for MacroVisitor<'_> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.in_function(&item.sig.ident, |visitor| {
+            syn::visit::visit_item_fn(visitor, item)
+        });
+    }
+
+    fn visit_impl_item_method(&mut self, item: &'ast syn::ImplItemMethod) {
+        self.in_function(&item.sig.ident, |visitor| {
+            syn::visit::visit_impl_item_method(visitor, item)
+        });
+    }
+
+    fn visit_trait_item_method(&mut self, item: &'ast syn::TraitItemMethod) {
+        self.in_function(&item.sig.ident, |visitor| {
+            syn::visit::visit_trait_item_method(visitor, item)
+        });
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let is_build_query = mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "build_query");
+        if !is_build_query {
+            return self.visit_macro_body(mac);
+        }
+
+        let (line, column) = call_site(&mac.tokens).unwrap_or_else(|| {
+            let start = mac.path.segments[0].ident.span().start();
+            (start.line, start.column + 1)
+        });
+        let location = format!("{}:{}:{}", self.file.display(), line, column);
+
+        match invocation(mac.tokens.clone()) {
+            Ok((dialect, style, variants, truncated)) => self.scan.invocations.push(Invocation {
+                file: self.file.to_owned(),
+                line,
+                column,
+                function: self.function.clone(),
+                dialect,
+                style,
+                variants,
+                truncated,
+            }),
+            Err(err) => self.scan.errors.push(ScanError {
+                location,
+                message: err.to_string(),
+            }),
+        }
+    }
+}
+
+type Parsed = (
+    Option<String>,
+    variants::PlaceholderStyle,
+    Vec<variants::Variant>,
+    bool,
+);
+
+fn invocation(tokens: proc_macro2::TokenStream) -> syn::Result<Parsed> {
+    let ast: parse::BuilderAST = syn::parse2(tokens)?;
+    let dialect = variants::dialect_attr(&ast.attrs)?;
+    let style = match &dialect {
+        Some(dialect) => variants::PlaceholderStyle::from_dialect(dialect),
+        None => variants::PlaceholderStyle::DollarFromZero,
+    };
+    let blocks = blocks::create_blocks(
+        ast.constituents,
+        blocks::Parent::root(),
+        &mut blocks::Counter::new(),
+    )?;
+    let (variants, truncated) = variants::enumerate(&blocks, variants::MAX_VARIANTS);
+    let dialect = dialect.as_ref().and_then(variants::dialect_name);
+    Ok((dialect, style, variants, truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_invocations() {
+        let dir =
+            std::env::temp_dir().join(format!("sql_builder_syntax_scan_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(
+            dir.join("repo.rs"),
+            r#"
+fn find(id: Option<i32>) -> Query {
+    sql_builder_test::build_query!(
+        #![dialect(Postgres)]
+        "SELECT * FROM t WHERE " if let Some(id) = id { "id = " id } else { "TRUE" }
+    )
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("target/ignored.rs"),
+            "fn f() { build_query!(\"X\") }",
+        )
+        .unwrap();
+
+        let scan = scan(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(scan.errors.is_empty());
+        assert_eq!(scan.invocations.len(), 1);
+        let invocation = &scan.invocations[0];
+        assert_eq!((invocation.line, invocation.column), (4, 9));
+        assert_eq!(invocation.function.as_deref(), Some("find"));
+        assert_eq!(invocation.dialect.as_deref(), Some("Postgres"));
+        let sql: Vec<_> = invocation
+            .variants
+            .iter()
+            .map(|variant| variant.render(invocation.style).sql)
+            .collect();
+        assert_eq!(
+            sql,
+            vec![
+                "SELECT * FROM t WHERE id = $1",
+                "SELECT * FROM t WHERE TRUE"
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_invocations_in_other_macros() {
+        let dir = std::env::temp_dir().join(format!(
+            "sql_builder_syntax_scan_macros_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("tests.rs"),
+            r#"
+#[test]
+fn renders() {
+    assert_eq!(build_query!("SELECT 1").sql, "SELECT 1");
+    let queries = vec![build_query!("SELECT 2"); 2];
+    println!("{}", build_query!("SELECT " if x { "3" }).sql);
+    macro_rules! select {
+        ($x:expr) => { build_query!("SELECT " $x) };
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let scan = scan(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let found: Vec<_> = scan
+            .invocations
+            .iter()
+            .map(|invocation| {
+                let sql = invocation.variants[0].render(invocation.style).sql;
+                (invocation.line, invocation.function.clone(), sql)
+            })
+            .collect();
+        let renders = Some("renders".to_owned());
+        assert_eq!(
+            found,
+            vec![
+                (4, renders.clone(), "SELECT 1".to_owned()),
+                (5, renders.clone(), "SELECT 2".to_owned()),
+                (6, renders, "SELECT 3".to_owned()),
+            ]
+        );
+        assert_eq!(scan.errors.len(), 1);
+        assert!(scan.errors[0].location.ends_with("tests.rs:7:5"));
+    }
+
+    #[test]
+    fn reports_unsupported_invocations() {
+        let dir = std::env::temp_dir().join(format!(
+            "sql_builder_syntax_scan_match_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("repo.rs"),
+            r#"
+fn find(kind: Kind) -> Query {
+    build_query!("SELECT * FROM t WHERE " match kind { Kind::A => { "a" } })
+}
+
+fn all() -> Query {
+    build_query!("SELECT * FROM t")
+}
+"#,
+        )
+        .unwrap();
+
+        let scan = scan(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(scan.invocations.len(), 1);
+        assert_eq!(scan.invocations[0].function.as_deref(), Some("all"));
+        assert_eq!(scan.errors.len(), 1);
+        assert!(scan.errors[0].location.ends_with("repo.rs:3:18"));
+        assert_eq!(scan.errors[0].message, "match is not supported yet");
+    }
+}
@@ -0,0 +1,552 @@
+use crate::blocks;
+use crate::parse;
+
+/// A piece of the SQL of one variant.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    Sql(String),
+    Arg,
+    /// `in expr`: one placeholder per item.
+    InList,
+    /// The row of a batch `for`, repeated once per row.
+    Rows(Vec<Piece>),
+    /// `..expr`: a query built elsewhere.
+    Subquery,
+    /// `..expr` as an item of a `where` or `or` group: a spliced predicate.
+    Predicate,
+    /// `order by term, ...`: ` ORDER BY` and columns chosen at runtime.
+    OrderBy,
+}
+
+/// One possible SQL shape of a `build_query!` invocation, i.e. one combination of
+/// branches taken.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Variant {
+    pub pieces: Vec<Piece>,
+}
+
+/// How a dialect writes placeholders, mirroring the runtime `Dialect` impls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaceholderStyle {
+    /// `$0`, `$1`, ... (`Generic`)
+    DollarFromZero,
+    /// `$1`, `$2`, ... (`Postgres`)
+    DollarFromOne,
+    /// `?1`, `?2`, ... (`Sqlite`)
+    QuestionFromOne,
+    /// `?`, for dialects the macro doesn't know.
+    Anonymous,
+}
+
+/// The last segment of the dialect's type path, e.g. `Postgres`.
+pub fn dialect_name(dialect: &syn::Type) -> Option<String> {
+    match dialect {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The dialect named by a `#![dialect(...)]` attribute, if any.
+pub fn dialect_attr(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::Type>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("dialect"))
+        .map(|attr| attr.parse_args())
+        .transpose()
+}
+
+impl PlaceholderStyle {
+    /// Guess the style from the name of the dialect.
+    pub fn from_dialect(dialect: &syn::Type) -> Self {
+        match dialect_name(dialect).as_deref() {
+            Some("Generic") => PlaceholderStyle::DollarFromZero,
+            Some("Postgres") => PlaceholderStyle::DollarFromOne,
+            Some("Sqlite") => PlaceholderStyle::QuestionFromOne,
+            _ => PlaceholderStyle::Anonymous,
+        }
+    }
+
+    fn write(self, index: usize, out: &mut String) {
+        match self {
+            PlaceholderStyle::DollarFromZero => out.push_str(&format!("${}", index)),
+            PlaceholderStyle::DollarFromOne => out.push_str(&format!("${}", index + 1)),
+            PlaceholderStyle::QuestionFromOne => out.push_str(&format!("?{}", index + 1)),
+            PlaceholderStyle::Anonymous => out.push('?'),
+        }
+    }
+}
+
+/// A variant rendered to text, with repeated parts (IN lists and batch rows)
+/// rendered once and followed by `, ...`, spliced queries as `/* subquery */`,
+/// spliced predicates as `/* predicate */` and runtime orderings as `/* order */`.
+#[derive(Debug, PartialEq)]
+pub struct Rendered {
+    pub sql: String,
+    /// Binds when every repeated part occurs once.
+    pub binds: usize,
+    /// Whether the bind count depends on runtime lengths or spliced queries.
+    pub repeated: bool,
+}
+
+impl Variant {
+    pub fn render(&self, style: PlaceholderStyle) -> Rendered {
+        self.render_with(style, false)
+    }
+
+    /// Render with every repeated part occurring exactly once and runtime
+    /// orderings as `1`, giving SQL a database can prepare. Spliced predicates
+    /// are `1` too, and other splices `SELECT NULL` right after a `(`, where
+    /// they are subqueries, or nothing, where they are whole clauses.
+    pub fn render_once(&self, style: PlaceholderStyle) -> Rendered {
+        self.render_with(style, true)
+    }
+
+    /// Whether anything is spliced in, whose SQL is only known at runtime.
+    pub fn has_splices(&self) -> bool {
+        fn any(pieces: &[Piece]) -> bool {
+            pieces.iter().any(|piece| match piece {
+                Piece::Subquery | Piece::Predicate => true,
+                Piece::Rows(row) => any(row),
+                _ => false,
+            })
+        }
+        any(&self.pieces)
+    }
+
+    fn render_with(&self, style: PlaceholderStyle, once: bool) -> Rendered {
+        fn render_pieces(
+            pieces: &[Piece],
+            style: PlaceholderStyle,
+            once: bool,
+            rendered: &mut Rendered,
+        ) {
+            let more = if once { "" } else { ", ..." };
+            for piece in pieces {
+                match piece {
+                    Piece::Sql(sql) => rendered.sql.push_str(sql),
+                    Piece::Arg => {
+                        style.write(rendered.binds, &mut rendered.sql);
+                        rendered.binds += 1;
+                    }
+                    Piece::InList => {
+                        rendered.sql.push('(');
+                        style.write(rendered.binds, &mut rendered.sql);
+                        rendered.sql.push_str(more);
+                        rendered.sql.push(')');
+                        rendered.binds += 1;
+                        rendered.repeated = true;
+                    }
+                    Piece::Rows(row) => {
+                        render_pieces(row, style, once, rendered);
+                        rendered.sql.push_str(more);
+                        rendered.repeated = true;
+                    }
+                    Piece::Subquery => {
+                        let subquery = if !once {
+                            "/* subquery */"
+                        } else if rendered.sql.trim_end().ends_with('(') {
+                            "SELECT NULL"
+                        } else {
+                            ""
+                        };
+                        rendered.sql.push_str(subquery);
+                        rendered.repeated = true;
+                    }
+                    Piece::Predicate => {
+                        rendered
+                            .sql
+                            .push_str(if once { "1" } else { "/* predicate */" });
+                        rendered.repeated = true;
+                    }
+                    Piece::OrderBy => {
+                        rendered.sql.push_str(" ORDER BY ");
+                        rendered
+                            .sql
+                            .push_str(if once { "1" } else { "/* order */" });
+                    }
+                }
+            }
+        }
+
+        let mut rendered = Rendered {
+            sql: String::new(),
+            binds: 0,
+            repeated: false,
+        };
+        render_pieces(&self.pieces, style, once, &mut rendered);
+        rendered
+    }
+}
+
+/// Upper bound on the variants enumerated for one invocation, by the CLI and in
+/// the query manifest alike.
+pub const MAX_VARIANTS: usize = 4096;
+
+/// All variants of `blocks`, in branch order, stopping after `limit`.
+///
+/// Returns whether the list was truncated.
+pub fn enumerate(blocks: &[blocks::Block], limit: usize) -> (Vec<Variant>, bool) {
+    let mut truncated = false;
+    let variants = enumerate_blocks(blocks, limit, &mut truncated);
+    (variants, truncated)
+}
+
+fn enumerate_blocks(blocks: &[blocks::Block], limit: usize, truncated: &mut bool) -> Vec<Variant> {
+    let mut variants = vec![Variant::default()];
+
+    for block in blocks {
+        match &block.op {
+            blocks::Op::Push(pushes) => {
+                let pieces = push_pieces(pushes);
+                for variant in &mut variants {
+                    variant.pieces.extend(pieces.iter().cloned());
+                }
+            }
+            blocks::Op::Branch(branches) => {
+                let mut alternatives = vec![];
+                for branch in branches {
+                    alternatives.extend(enumerate_blocks(&branch.then, limit, truncated));
+                }
+                // Without an `else`, not taking any branch is a variant too.
+                if branches.last().is_none_or(|branch| branch.cond.is_some()) {
+                    alternatives.push(Variant::default());
+                }
+
+                variants = product(&variants, &alternatives, limit, truncated);
+            }
+            blocks::Op::Batch(batch) => {
+                // Rows can only contain pushes, so there is exactly one row shape.
+                let row = enumerate_blocks(&batch.row, limit, truncated)
+                    .pop()
+                    .unwrap_or_default();
+                for variant in &mut variants {
+                    variant.pieces.push(Piece::Rows(row.pieces.clone()));
+                }
+            }
+            blocks::Op::InList(_) => {
+                for variant in &mut variants {
+                    variant.pieces.push(Piece::InList);
+                }
+            }
+            blocks::Op::Splice(_) => {
+                for variant in &mut variants {
+                    variant.pieces.push(Piece::Subquery);
+                }
+            }
+            blocks::Op::OrderBy(_) => {
+                for variant in &mut variants {
+                    variant.pieces.push(Piece::OrderBy);
+                }
+            }
+            blocks::Op::Group(group) => {
+                let alternatives = enumerate_group(group, limit, truncated);
+                variants = product(&variants, &alternatives, limit, truncated);
+            }
+        }
+    }
+
+    variants
+}
+
+fn product(
+    variants: &[Variant],
+    alternatives: &[Variant],
+    limit: usize,
+    truncated: &mut bool,
+) -> Vec<Variant> {
+    let mut product = vec![];
+    for variant in variants {
+        for alternative in alternatives {
+            if product.len() == limit {
+                *truncated = true;
+                return product;
+            }
+            let mut pieces = variant.pieces.clone();
+            pieces.extend(alternative.pieces.iter().cloned());
+            product.push(Variant { pieces });
+        }
+    }
+    product
+}
+
+/// Every way a group can render, joining the items that render anything.
+fn enumerate_group(group: &blocks::Group, limit: usize, truncated: &mut bool) -> Vec<Variant> {
+    // Each partial variant, and whether the group has been opened in it.
+    let mut partials = vec![(Variant::default(), false)];
+    for item in &group.items {
+        let alternatives = match &item[..] {
+            [blocks::Block {
+                op: blocks::Op::Splice(_),
+                ..
+            }] if group.kind != parse::GroupKind::Set => vec![Variant {
+                pieces: vec![Piece::Predicate],
+            }],
+            _ => enumerate_blocks(item, limit, truncated),
+        };
+        let mut next = vec![];
+        'outer: for (variant, opened) in &partials {
+            for alternative in &alternatives {
+                if next.len() == limit {
+                    *truncated = true;
+                    break 'outer;
+                }
+                let renders_nothing = alternative
+                    .pieces
+                    .iter()
+                    .all(|piece| matches!(piece, Piece::Sql(sql) if sql.is_empty()));
+                if renders_nothing {
+                    next.push((variant.clone(), *opened));
+                    continue;
+                }
+                let mut pieces = variant.pieces.clone();
+                let joint = if *opened {
+                    group.kind.separator()
+                } else {
+                    group.kind.open()
+                };
+                pieces.push(Piece::Sql(joint.to_owned()));
+                pieces.extend(alternative.pieces.iter().cloned());
+                next.push((Variant { pieces }, true));
+            }
+        }
+        partials = next;
+    }
+    // An empty `set` fails to build, so isn't a variant.
+    partials
+        .into_iter()
+        .filter(|(_, opened)| *opened || group.kind != parse::GroupKind::Set)
+        .map(|(mut variant, opened)| {
+            if opened && !group.kind.close().is_empty() {
+                variant
+                    .pieces
+                    .push(Piece::Sql(group.kind.close().to_owned()));
+            }
+            variant
+        })
+        .collect()
+}
+
+fn push_pieces(pushes: &[blocks::Push]) -> Vec<Piece> {
+    let mut pieces: Vec<Piece> = vec![];
+    for push in pushes {
+        match push {
+            blocks::Push::Lit(lit_str) => match pieces.last_mut() {
+                Some(Piece::Sql(sql)) => sql.push_str(&lit_str.value()),
+                _ => pieces.push(Piece::Sql(lit_str.value())),
+            },
+            blocks::Push::Bind(_) => pieces.push(Piece::Arg),
+            blocks::Push::Empty => {}
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use quote::quote;
+
+    fn test_render(ast: proc_macro2::TokenStream, style: PlaceholderStyle) -> Vec<String> {
+        let ast: parse::BuilderAST = syn::parse2(ast).unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let (variants, truncated) = enumerate(&blocks, 100);
+        assert!(!truncated);
+        variants
+            .iter()
+            .map(|variant| variant.render(style).sql)
+            .collect()
+    }
+
+    #[test]
+    fn enumerates_branches() {
+        let variants = test_render(
+            quote! {
+                "SELECT * FROM t WHERE "
+                if let Some(a) = a { "a = " a } else { "TRUE" }
+                if b { " AND b = " b }
+            },
+            PlaceholderStyle::DollarFromOne,
+        );
+        assert_eq!(
+            variants,
+            vec![
+                "SELECT * FROM t WHERE a = $1 AND b = $2",
+                "SELECT * FROM t WHERE a = $1",
+                "SELECT * FROM t WHERE TRUE AND b = $1",
+                "SELECT * FROM t WHERE TRUE",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_repeated_parts_once() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "INSERT INTO t (a) SELECT a FROM u WHERE a IN " in ids
+            for a in rows { "(" a ")" }
+        })
+        .unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let (variants, _) = enumerate(&blocks, 100);
+        assert_eq!(
+            variants[0].render(PlaceholderStyle::QuestionFromOne),
+            Rendered {
+                sql: "INSERT INTO t (a) SELECT a FROM u WHERE a IN (?1, ...)(?2), ...".to_owned(),
+                binds: 2,
+                repeated: true,
+            }
+        );
+        assert_eq!(
+            variants[0]
+                .render_once(PlaceholderStyle::QuestionFromOne)
+                .sql,
+            "INSERT INTO t (a) SELECT a FROM u WHERE a IN (?1)(?2)"
+        );
+    }
+
+    #[test]
+    fn renders_spliced_queries_as_placeholders() {
+        let variants = test_render(
+            quote! { "SELECT * FROM t WHERE a = " a " AND id IN (" ..ids ")" },
+            PlaceholderStyle::DollarFromOne,
+        );
+        assert_eq!(
+            variants,
+            vec!["SELECT * FROM t WHERE a = $1 AND id IN (/* subquery */)"]
+        );
+    }
+
+    #[test]
+    fn renders_splices_by_position_once() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t"
+            where { "a = " a ..filter }
+            " AND id IN (" ..ids ")"
+            ..page
+        })
+        .unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let (variants, _) = enumerate(&blocks, 100);
+        assert!(variants[0].has_splices());
+        assert_eq!(
+            variants[0].render(PlaceholderStyle::QuestionFromOne).sql,
+            "SELECT * FROM t WHERE a = ?1 AND /* predicate */ AND id IN (/* subquery */)/* subquery */"
+        );
+        assert_eq!(
+            variants[0]
+                .render_once(PlaceholderStyle::QuestionFromOne)
+                .sql,
+            "SELECT * FROM t WHERE a = ?1 AND 1 AND id IN (SELECT NULL)"
+        );
+    }
+
+    #[test]
+    fn renders_runtime_orderings_as_placeholders() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            "SELECT * FROM t WHERE a = " a order by sort, Column::Id " LIMIT " limit
+        })
+        .unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let (variants, _) = enumerate(&blocks, 100);
+        let rendered = variants[0].render(PlaceholderStyle::DollarFromOne);
+        assert_eq!(
+            rendered.sql,
+            "SELECT * FROM t WHERE a = $1 ORDER BY /* order */ LIMIT $2"
+        );
+        assert!(!rendered.repeated);
+        assert_eq!(
+            variants[0].render_once(PlaceholderStyle::DollarFromOne).sql,
+            "SELECT * FROM t WHERE a = $1 ORDER BY 1 LIMIT $2"
+        );
+    }
+
+    #[test]
+    fn joins_group_items_present() {
+        let variants = test_render(
+            quote! {
+                "SELECT * FROM t"
+                where {
+                    if a { "a = " a }
+                    or { if b { "b = " b } "c" }
+                }
+            },
+            PlaceholderStyle::DollarFromOne,
+        );
+        assert_eq!(
+            variants,
+            vec![
+                "SELECT * FROM t WHERE a = $1 AND (b = $2 OR c)",
+                "SELECT * FROM t WHERE a = $1 AND (c)",
+                "SELECT * FROM t WHERE (b = $1 OR c)",
+                "SELECT * FROM t WHERE (c)",
+            ]
+        );
+
+        let variants = test_render(
+            quote! { "SELECT * FROM t" where { if a { "a" } } },
+            PlaceholderStyle::DollarFromOne,
+        );
+        assert_eq!(variants, vec!["SELECT * FROM t WHERE a", "SELECT * FROM t"]);
+    }
+
+    #[test]
+    fn leaves_out_empty_sets() {
+        let variants = test_render(
+            quote! {
+                "UPDATE t"
+                set { if a { "a = " a } if b { "b = " b } }
+                where { "id = " id }
+            },
+            PlaceholderStyle::DollarFromOne,
+        );
+        assert_eq!(
+            variants,
+            vec![
+                "UPDATE t SET a = $1, b = $2 WHERE id = $3",
+                "UPDATE t SET a = $1 WHERE id = $2",
+                "UPDATE t SET b = $1 WHERE id = $2",
+            ]
+        );
+    }
+
+    #[test]
+    fn truncates_at_limit() {
+        let ast: parse::BuilderAST = syn::parse2(quote! {
+            if a { "A" } if b { "B" } if c { "C" }
+        })
+        .unwrap();
+        let blocks = blocks::create_blocks(
+            ast.constituents,
+            blocks::Parent::root(),
+            &mut blocks::Counter::new(),
+        )
+        .unwrap();
+        let (variants, truncated) = enumerate(&blocks, 5);
+        assert_eq!(variants.len(), 5);
+        assert!(truncated);
+    }
+}
@@ -0,0 +1,555 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::any::Any;
+#[cfg(feature = "alloc")]
+use core::convert::TryInto;
+use core::fmt;
+
+/// The arguments used when none are given: [`Values`] with `alloc`, otherwise
+/// `()`, which discards them.
+///
+/// `Values` allocates its `Vec`, and a `String` or `Vec<u8>` for each text or
+/// bytes value, making a query with a few binds take about a third longer to build
+/// than with `()` (`cargo bench --bench args`). Use `#![args(())]` where only the
+/// SQL is wanted, or [`PackedArgs`] for one allocation.
+#[cfg(feature = "alloc")]
+pub type DefaultArgs = Values;
+
+#[cfg(not(feature = "alloc"))]
+pub type DefaultArgs = ();
+
+/// A bind value as it is handed to [`Arguments`], borrowed from the bound expression.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    Null,
+    Bool(bool),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    /// A `u64` or `usize`, which no signed type holds all of.
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Text(&'a str),
+    Bytes(&'a [u8]),
+    /// A value of a type of its own, such as a UUID or a timestamp.
+    Custom(&'a dyn CustomValue),
+}
+
+impl ValueRef<'_> {
+    /// The size of the value in bytes as drivers send it: the width of numbers,
+    /// the length of text and bytes, and nothing for NULL. Custom values count
+    /// their fallback.
+    pub fn size(&self) -> usize {
+        match self {
+            ValueRef::Null => 0,
+            ValueRef::Bool(_) => 1,
+            ValueRef::I16(_) => 2,
+            ValueRef::I32(_) | ValueRef::F32(_) => 4,
+            ValueRef::I64(_) | ValueRef::U64(_) | ValueRef::F64(_) => 8,
+            ValueRef::Text(value) => value.len(),
+            ValueRef::Bytes(value) => value.len(),
+            ValueRef::Custom(value) => value.fallback().size(),
+        }
+    }
+}
+
+/// A bind value of a type other than those of [`ValueRef`], for drivers that have
+/// one for it, such as `uuid::Uuid`. A [`FromValue`] implementation for such a
+/// driver downcasts [`as_any`](Self::as_any) to the types it knows; everything
+/// else binds the [`fallback`](Self::fallback).
+///
+/// ```
+/// # use std::any::Any;
+/// # use sql_builder_test::{build_query, CustomValue, Encode, Value, ValueRef};
+/// #[derive(Debug)]
+/// struct Uuid([u8; 16]);
+///
+/// impl CustomValue for Uuid {
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+///
+///     fn fallback(&self) -> ValueRef<'_> {
+///         ValueRef::Bytes(&self.0)
+///     }
+/// }
+///
+/// impl Encode for Uuid {
+///     fn encode(&self) -> ValueRef<'_> {
+///         ValueRef::Custom(self)
+///     }
+/// }
+///
+/// // `Values` has no UUIDs, so it keeps the fallback.
+/// let query = build_query!("SELECT * FROM users WHERE id = " Uuid([7; 16]));
+/// assert_eq!(query.args.0, [Value::Bytes(vec![7; 16])]);
+/// ```
+pub trait CustomValue: fmt::Debug + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    /// The value as one of the other variants of [`ValueRef`], never `Custom`.
+    fn fallback(&self) -> ValueRef<'_>;
+}
+
+/// Custom values are equal when their fallbacks are.
+impl PartialEq for dyn CustomValue + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.fallback() == other.fallback()
+    }
+}
+
+/// A type that can be bound in `build_query!`.
+///
+/// Unsigned integers are widened to the next signed type that holds all their
+/// values, except for `u64` and `usize`; `None` binds NULL.
+pub trait Encode {
+    fn encode(&self) -> ValueRef<'_>;
+}
+
+macro_rules! encode {
+    ($($ty:ty => $variant:ident,)*) => {$(
+        impl Encode for $ty {
+            #[inline]
+            fn encode(&self) -> ValueRef<'_> {
+                ValueRef::$variant((*self).into())
+            }
+        }
+    )*};
+}
+
+encode! {
+    bool => Bool,
+    i8 => I16,
+    u8 => I16,
+    i16 => I16,
+    u16 => I32,
+    i32 => I32,
+    u32 => I64,
+    i64 => I64,
+    u64 => U64,
+    f32 => F32,
+    f64 => F64,
+}
+
+impl Encode for usize {
+    #[inline]
+    fn encode(&self) -> ValueRef<'_> {
+        ValueRef::U64(*self as u64)
+    }
+}
+
+impl Encode for str {
+    fn encode(&self) -> ValueRef<'_> {
+        ValueRef::Text(self)
+    }
+}
+
+impl Encode for [u8] {
+    fn encode(&self) -> ValueRef<'_> {
+        ValueRef::Bytes(self)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self) -> ValueRef<'_> {
+        match self {
+            Some(value) => value.encode(),
+            None => ValueRef::Null,
+        }
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    #[inline]
+    fn encode(&self) -> ValueRef<'_> {
+        (**self).encode()
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &mut T {
+    #[inline]
+    fn encode(&self) -> ValueRef<'_> {
+        (**self).encode()
+    }
+}
+
+impl Encode for ValueRef<'_> {
+    fn encode(&self) -> ValueRef<'_> {
+        *self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Encode for String {
+    fn encode(&self) -> ValueRef<'_> {
+        ValueRef::Text(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Encode for Vec<u8> {
+    fn encode(&self) -> ValueRef<'_> {
+        ValueRef::Bytes(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Encode + ?Sized> Encode for Box<T> {
+    fn encode(&self) -> ValueRef<'_> {
+        (**self).encode()
+    }
+}
+
+/// Where a builder puts the values it binds, in placeholder order.
+pub trait Arguments: Default {
+    fn add<T: Encode + ?Sized>(&mut self, value: &T);
+
+    /// How many values were added.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take over the values of a spliced query, which follow these.
+    fn append(&mut self, other: Self);
+}
+
+/// Arguments that keep their values, to read back in order, such as to send them
+/// over the wire or record them.
+pub trait ReadArguments: Arguments {
+    type Values<'a>: Iterator<Item = ValueRef<'a>>
+    where
+        Self: 'a;
+
+    fn values(&self) -> Self::Values<'_>;
+}
+
+/// Discards the values, when only the SQL is wanted.
+impl Arguments for () {
+    #[inline]
+    fn add<T: Encode + ?Sized>(&mut self, _value: &T) {}
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn append(&mut self, _other: Self) {}
+}
+
+/// An owned bind value.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    /// A `u64` or `usize`, which no signed type holds all of.
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+#[cfg(feature = "alloc")]
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Value::Null,
+            ValueRef::Bool(value) => Value::Bool(value),
+            ValueRef::I16(value) => Value::I16(value),
+            ValueRef::I32(value) => Value::I32(value),
+            ValueRef::I64(value) => Value::I64(value),
+            ValueRef::U64(value) => Value::U64(value),
+            ValueRef::F32(value) => Value::F32(value),
+            ValueRef::F64(value) => Value::F64(value),
+            ValueRef::Text(value) => Value::Text(value.into()),
+            ValueRef::Bytes(value) => Value::Bytes(value.into()),
+            ValueRef::Custom(value) => value.fallback().into(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Encode for Value {
+    fn encode(&self) -> ValueRef<'_> {
+        match self {
+            Value::Null => ValueRef::Null,
+            Value::Bool(value) => ValueRef::Bool(*value),
+            Value::I16(value) => ValueRef::I16(*value),
+            Value::I32(value) => ValueRef::I32(*value),
+            Value::I64(value) => ValueRef::I64(*value),
+            Value::U64(value) => ValueRef::U64(*value),
+            Value::F32(value) => ValueRef::F32(*value),
+            Value::F64(value) => ValueRef::F64(*value),
+            Value::Text(value) => ValueRef::Text(value),
+            Value::Bytes(value) => ValueRef::Bytes(value),
+        }
+    }
+}
+
+/// A driver's own value type, for [`Native`] to convert binds into. Values of
+/// types the driver has but [`ValueRef`] hasn't come as [`ValueRef::Custom`].
+///
+/// ```
+/// # use sql_builder_test::{build_query, FromValue, Native, ValueRef};
+/// #[derive(Debug, PartialEq)]
+/// enum DriverValue {
+///     Null,
+///     Integer(i64),
+///     Text(String),
+/// }
+///
+/// impl FromValue for DriverValue {
+///     fn from_value(value: ValueRef<'_>) -> Self {
+///         match value {
+///             ValueRef::I16(value) => Self::Integer(value.into()),
+///             ValueRef::I32(value) => Self::Integer(value.into()),
+///             ValueRef::I64(value) => Self::Integer(value),
+///             ValueRef::Text(text) => Self::Text(text.to_owned()),
+///             // ...
+///             _ => Self::Null,
+///         }
+///     }
+/// }
+///
+/// let name = "kim";
+/// let query = build_query!(
+///     #![args(Native<DriverValue>)]
+///     "SELECT * FROM users WHERE org = " 7 " AND name = " name
+/// );
+/// assert_eq!(
+///     query.args.0,
+///     [DriverValue::Integer(7), DriverValue::Text("kim".into())]
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub trait FromValue {
+    fn from_value(value: ValueRef<'_>) -> Self;
+}
+
+#[cfg(feature = "alloc")]
+impl FromValue for Value {
+    fn from_value(value: ValueRef<'_>) -> Self {
+        value.into()
+    }
+}
+
+/// The values converted into `V` as they are bound, ready to hand to the driver
+/// `V` belongs to.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Native<V>(pub Vec<V>);
+
+#[cfg(feature = "alloc")]
+impl<V> Default for Native<V> {
+    fn default() -> Self {
+        Native(Vec::new())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<V: FromValue> Arguments for Native<V> {
+    fn add<T: Encode + ?Sized>(&mut self, value: &T) {
+        self.0.push(V::from_value(value.encode()));
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn append(&mut self, mut other: Self) {
+        self.0.append(&mut other.0);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, V> IntoIterator for &'a Native<V> {
+    type Item = &'a V;
+    type IntoIter = core::slice::Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<V: FromValue + Encode> ReadArguments for Native<V> {
+    type Values<'a>
+        = core::iter::Map<core::slice::Iter<'a, V>, fn(&'a V) -> ValueRef<'a>>
+    where
+        V: 'a;
+
+    fn values(&self) -> Self::Values<'_> {
+        self.0.iter().map(V::encode)
+    }
+}
+
+/// The values as dynamically typed [`Value`]s.
+#[cfg(feature = "alloc")]
+pub type Values = Native<Value>;
+
+/// The values packed into one byte buffer, each a tag byte followed by the value
+/// in little endian, with text and bytes prefixed by their `u32` length.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackedArgs {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+mod tag {
+    pub const NULL: u8 = 0;
+    pub const BOOL: u8 = 1;
+    pub const I16: u8 = 2;
+    pub const I32: u8 = 3;
+    pub const I64: u8 = 4;
+    pub const U64: u8 = 5;
+    pub const F32: u8 = 6;
+    pub const F64: u8 = 7;
+    pub const TEXT: u8 = 8;
+    pub const BYTES: u8 = 9;
+}
+
+#[cfg(feature = "alloc")]
+impl PackedArgs {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// The values, decoded from the buffer.
+    pub fn iter(&self) -> PackedIter<'_> {
+        PackedIter { rest: &self.bytes }
+    }
+
+    fn push_slice(&mut self, tag: u8, slice: &[u8]) {
+        let len: u32 = slice
+            .len()
+            .try_into()
+            .expect("bind value longer than u32::MAX bytes");
+        self.bytes.push(tag);
+        self.bytes.extend_from_slice(&len.to_le_bytes());
+        self.bytes.extend_from_slice(slice);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Arguments for PackedArgs {
+    fn add<T: Encode + ?Sized>(&mut self, value: &T) {
+        match value.encode() {
+            ValueRef::Null => self.bytes.push(tag::NULL),
+            ValueRef::Bool(value) => self.bytes.extend_from_slice(&[tag::BOOL, value as u8]),
+            ValueRef::I16(value) => {
+                self.bytes.push(tag::I16);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::I32(value) => {
+                self.bytes.push(tag::I32);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::I64(value) => {
+                self.bytes.push(tag::I64);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::U64(value) => {
+                self.bytes.push(tag::U64);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::F32(value) => {
+                self.bytes.push(tag::F32);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::F64(value) => {
+                self.bytes.push(tag::F64);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ValueRef::Text(value) => self.push_slice(tag::TEXT, value.as_bytes()),
+            ValueRef::Bytes(value) => self.push_slice(tag::BYTES, value),
+            ValueRef::Custom(value) => return self.add(&value.fallback()),
+        }
+        self.len += 1;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn append(&mut self, other: Self) {
+        self.bytes.extend_from_slice(&other.bytes);
+        self.len += other.len;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> IntoIterator for &'a PackedArgs {
+    type Item = ValueRef<'a>;
+    type IntoIter = PackedIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ReadArguments for PackedArgs {
+    type Values<'a> = PackedIter<'a>;
+
+    fn values(&self) -> Self::Values<'_> {
+        self.iter()
+    }
+}
+
+/// The values of [`PackedArgs`], in order.
+#[cfg(feature = "alloc")]
+pub struct PackedIter<'a> {
+    rest: &'a [u8],
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> PackedIter<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (bytes, rest) = self.rest.split_at(N);
+        self.rest = rest;
+        bytes.try_into().unwrap()
+    }
+
+    fn take_slice(&mut self) -> &'a [u8] {
+        let len = u32::from_le_bytes(self.take()) as usize;
+        let (bytes, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for PackedIter<'a> {
+    type Item = ValueRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&tag, rest) = self.rest.split_first()?;
+        self.rest = rest;
+        // Only `PackedArgs::add` writes the buffer, so it is always well formed.
+        Some(match tag {
+            tag::NULL => ValueRef::Null,
+            tag::BOOL => ValueRef::Bool(self.take::<1>()[0] != 0),
+            tag::I16 => ValueRef::I16(i16::from_le_bytes(self.take())),
+            tag::I32 => ValueRef::I32(i32::from_le_bytes(self.take())),
+            tag::I64 => ValueRef::I64(i64::from_le_bytes(self.take())),
+            tag::U64 => ValueRef::U64(u64::from_le_bytes(self.take())),
+            tag::F32 => ValueRef::F32(f32::from_le_bytes(self.take())),
+            tag::F64 => ValueRef::F64(f64::from_le_bytes(self.take())),
+            tag::TEXT => ValueRef::Text(core::str::from_utf8(self.take_slice()).unwrap()),
+            tag::BYTES => ValueRef::Bytes(self.take_slice()),
+            _ => unreachable!("unknown tag {}", tag),
+        })
+    }
+}
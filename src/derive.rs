@@ -0,0 +1,159 @@
+//! The traits implemented by the derives.
+
+use crate::{Arguments, BuildError, Builder, DefaultSink, Dialect, Query, Sink};
+#[cfg(feature = "alloc")]
+use crate::{Splice, SqlFragment};
+
+/// A struct that `#[derive(SqlInsert)]` renders an `INSERT` of.
+///
+/// ```
+/// # use sql_builder_test::{Postgres, SqlInsert};
+/// #[derive(SqlInsert)]
+/// #[sql(table = "users", returning = "id")]
+/// struct NewUser {
+///     name: String,
+///     #[sql(rename = "email_address")]
+///     email: String,
+///     // Left out when `None`, for the column default.
+///     #[sql(default)]
+///     role: Option<String>,
+///     #[sql(skip)]
+///     password: String,
+/// }
+///
+/// let user = NewUser {
+///     name: "kim".into(),
+///     email: "kim@example.com".into(),
+///     role: None,
+///     password: "hunter2".into(),
+/// };
+/// assert_eq!(
+///     user.insert_query::<Postgres>().sql,
+///     "INSERT INTO users (name, email_address) VALUES ($1, $2) RETURNING id"
+/// );
+/// ```
+pub trait SqlInsert {
+    /// Render the `INSERT` statement into `builder`, binding the fields.
+    fn push_insert<S: Sink, D: Dialect, A: Arguments>(&self, builder: &mut Builder<S, D, A>);
+
+    /// The `INSERT` statement, with `D` placeholders.
+    fn insert_query<D: Dialect>(&self) -> Query {
+        let mut builder = Builder::<DefaultSink, D>::with_sink(DefaultSink::default());
+        self.push_insert(&mut builder);
+        builder.build()
+    }
+}
+
+/// A patch struct that `#[derive(SqlUpdate)]` renders an `UPDATE` of: `Option`
+/// fields are assigned only when `Some`, other fields always, and `key` fields
+/// make up the `WHERE` clause.
+///
+/// ```
+/// # use sql_builder_test::{Postgres, SqlUpdate};
+/// #[derive(SqlUpdate)]
+/// #[sql(table = "users")]
+/// struct PatchUser {
+///     #[sql(key)]
+///     id: i64,
+///     name: Option<String>,
+///     // `Some(None)` sets the column to NULL.
+///     nickname: Option<Option<String>>,
+/// }
+///
+/// let patch = PatchUser {
+///     id: 7,
+///     name: None,
+///     nickname: Some(None),
+/// };
+/// assert_eq!(
+///     patch.update_query::<Postgres>()?.sql,
+///     "UPDATE users SET nickname = $1 WHERE id = $2"
+/// );
+/// # Ok::<(), sql_builder_test::BuildError>(())
+/// ```
+pub trait SqlUpdate {
+    /// Render the `UPDATE` statement into `builder`, binding the fields.
+    fn push_update<S: Sink, D: Dialect, A: Arguments>(&self, builder: &mut Builder<S, D, A>);
+
+    /// The `UPDATE` statement, with `D` placeholders. Fails with
+    /// [`BuildError::EmptySet`] when there is nothing to update.
+    fn update_query<D: Dialect>(&self) -> Result<Query, BuildError> {
+        let mut builder = Builder::<DefaultSink, D>::with_sink(DefaultSink::default());
+        self.push_update(&mut builder);
+        builder.try_build()
+    }
+}
+
+/// A filter struct that `#[derive(SqlFilter)]` renders the predicates of, joined
+/// with `AND`. `Option` fields are left out when `None`, `Vec` fields are matched
+/// with `IN` and left out when empty, and other fields compare with `=` unless
+/// given another operator: `eq`, `ne`, `gt`, `gte`, `lt`, `lte`, `like` or
+/// `ilike`, optionally naming the column. `ilike` renders as `LIKE` on SQLite,
+/// which has no `ILIKE` but ignores the case of ASCII letters in `LIKE`.
+///
+/// ```
+/// # use sql_builder_test::{build_query, Postgres, SqlFilter};
+/// #[derive(SqlFilter)]
+/// struct UserFilter {
+///     org_id: i64,
+///     role: Option<String>,
+///     id: Option<Vec<i64>>,
+///     #[sql(gte = "created_at")]
+///     since: Option<i64>,
+///     #[sql(ilike = "name")]
+///     search: Option<String>,
+/// }
+///
+/// let filter = UserFilter {
+///     org_id: 1,
+///     role: None,
+///     id: Some(vec![2, 3]),
+///     since: None,
+///     search: Some("%kim%".into()),
+/// };
+/// let query = build_query!(
+///     #![dialect(Postgres)]
+///     "SELECT * FROM users" where { ..filter.predicates() }
+/// );
+/// assert_eq!(
+///     query.sql,
+///     "SELECT * FROM users WHERE org_id = $1 AND id IN ($2, $3) AND name ILIKE $4"
+/// );
+/// ```
+pub trait SqlFilter {
+    /// Render the predicates present into `builder`, or nothing if none are.
+    fn push_filter<S: Sink, D: Dialect, A: Arguments>(&self, builder: &mut Builder<S, D, A>);
+
+    /// The predicates present, to splice into `build_query!`, typically as an
+    /// item of a `where` group, rendered for the dialect of the query.
+    #[cfg(feature = "alloc")]
+    fn predicates(&self) -> FilterPredicates<'_, Self>
+    where
+        Self: Sized,
+    {
+        FilterPredicates { filter: self }
+    }
+
+    /// The predicates present as a fragment. Fragments don't know the dialect
+    /// they are spliced into, so `ilike` renders as `ILIKE`, which SQLite lacks;
+    /// splice [`predicates`](Self::predicates) there instead.
+    #[cfg(feature = "alloc")]
+    fn filter_fragment(&self) -> SqlFragment {
+        let mut builder = Builder::fragment();
+        self.push_filter(&mut builder);
+        builder.into_fragment()
+    }
+}
+
+/// What [`SqlFilter::predicates`] splices in.
+#[cfg(feature = "alloc")]
+pub struct FilterPredicates<'a, F> {
+    filter: &'a F,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: SqlFilter, A: Arguments> Splice<A> for FilterPredicates<'_, F> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        self.filter.push_filter(builder);
+    }
+}
@@ -0,0 +1,78 @@
+use core::fmt;
+
+use crate::sink::{FixedBuf, FmtSink};
+
+/// SQL dialect specifics the builder needs while rendering.
+pub trait Dialect {
+    /// The maximum number of bind parameters in one statement.
+    const MAX_PARAMS: usize;
+
+    /// Whether `IN ()` with no items is valid SQL.
+    const EMPTY_IN_LIST: bool;
+
+    /// Whether row values compare lexicographically, as in `(a, b) > (1, 2)`.
+    const ROW_VALUES: bool;
+
+    /// The operator matching a `LIKE` pattern regardless of case.
+    const ILIKE: &'static str;
+
+    /// Write the placeholder for the bind parameter at zero-based `index`.
+    fn write_placeholder<W: fmt::Write>(index: usize, out: &mut W) -> fmt::Result;
+}
+
+/// Whether `D` numbers placeholders `$1`, `$2`, ... as the Postgres wire protocol expects.
+pub(crate) fn numbers_like_postgres<D: Dialect>() -> bool {
+    let mut first = FixedBuf::<4>::new();
+    let written = D::write_placeholder(
+        0,
+        &mut FmtSink {
+            sink: &mut first,
+            len: 0,
+        },
+    );
+    written.is_ok() && first.as_str() == "$1"
+}
+
+/// `$0`, `$1`, ... without any parameter limit. This is what `build_query!` renders
+/// unless told otherwise with `#![dialect(...)]`.
+pub struct Generic;
+
+impl Dialect for Generic {
+    const MAX_PARAMS: usize = usize::MAX;
+    const EMPTY_IN_LIST: bool = false;
+    const ROW_VALUES: bool = false;
+    const ILIKE: &'static str = " ILIKE ";
+
+    fn write_placeholder<W: fmt::Write>(index: usize, out: &mut W) -> fmt::Result {
+        write!(out, "${}", index)
+    }
+}
+
+/// `$1`, `$2`, ... limited to the 65535 parameters of the wire protocol.
+pub struct Postgres;
+
+impl Dialect for Postgres {
+    const MAX_PARAMS: usize = 65535;
+    const EMPTY_IN_LIST: bool = false;
+    const ROW_VALUES: bool = true;
+    const ILIKE: &'static str = " ILIKE ";
+
+    fn write_placeholder<W: fmt::Write>(index: usize, out: &mut W) -> fmt::Result {
+        write!(out, "${}", index + 1)
+    }
+}
+
+/// `?1`, `?2`, ... limited to SQLite's default `SQLITE_MAX_VARIABLE_NUMBER` of 999.
+pub struct Sqlite;
+
+impl Dialect for Sqlite {
+    const MAX_PARAMS: usize = 999;
+    const EMPTY_IN_LIST: bool = true;
+    const ROW_VALUES: bool = true;
+    // `LIKE` ignores the case of ASCII letters, and there is no `ILIKE`.
+    const ILIKE: &'static str = " LIKE ";
+
+    fn write_placeholder<W: fmt::Write>(index: usize, out: &mut W) -> fmt::Result {
+        write!(out, "?{}", index + 1)
+    }
+}
@@ -0,0 +1,32 @@
+use core::fmt;
+
+/// Why a query could not be built.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// More placeholders were rendered than the dialect allows in one statement.
+    TooManyParameters { count: usize, max: usize },
+    /// An `IN` list had no items, and the dialect can't express `IN ()`.
+    EmptyInList,
+    /// The sink ran out of capacity.
+    SinkOverflow,
+    /// A `set { ... }` group had no assignments, leaving nothing to update.
+    EmptySet,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::TooManyParameters { count, max } => write!(
+                f,
+                "query has {} parameters, but the dialect allows at most {}",
+                count, max
+            ),
+            BuildError::EmptyInList => write!(f, "empty IN list is not supported by the dialect"),
+            BuildError::SinkOverflow => write!(f, "SQL sink overflowed while building query"),
+            BuildError::EmptySet => write!(f, "SET has no assignments, nothing to update"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
@@ -0,0 +1,273 @@
+//! One async interface for running queries, whatever the backend.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::pin;
+
+use futures_core::Stream;
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use crate::{MockError, MockExecutor, Query, ReadArguments, Value};
+
+#[cfg(feature = "tokio-postgres")]
+mod postgres;
+#[cfg(feature = "rusqlite")]
+mod sqlite;
+
+/// Why running a query failed.
+#[derive(Debug)]
+pub enum ExecuteError {
+    /// `fetch_one` got no rows.
+    RowNotFound,
+    /// A bound value the backend has no type for, such as a `u64` above
+    /// `i64::MAX` for SQLite.
+    UnsupportedValue(Value),
+    /// An error from the backend.
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ExecuteError {
+    pub(crate) fn backend(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        ExecuteError::Backend(Box::new(error))
+    }
+}
+
+impl fmt::Display for ExecuteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecuteError::RowNotFound => write!(f, "query returned no rows"),
+            ExecuteError::UnsupportedValue(value) => {
+                write!(f, "backend can't bind {:?}", value)
+            }
+            ExecuteError::Backend(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ExecuteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExecuteError::Backend(error) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<MockError> for ExecuteError {
+    fn from(error: MockError) -> Self {
+        ExecuteError::backend(error)
+    }
+}
+
+/// Runs queries against a backend, returning rows as dynamically typed values, so
+/// code taking an `impl Executor` runs against any of them, or a [`MockExecutor`].
+///
+/// Queries must be rendered in the backend's dialect and keep their values, as
+/// [`Values`](crate::Values) and [`PackedArgs`](crate::PackedArgs) do.
+///
+/// ```
+/// # use sql_builder_test::{build_query, ExecuteError, Executor, MockExecutor, Value};
+/// async fn find_user(db: &mut impl Executor, id: i64) -> Result<Option<Vec<Value>>, ExecuteError> {
+///     db.fetch_optional(&build_query!("SELECT * FROM users WHERE id = " id)).await
+/// }
+///
+/// let mut mock = MockExecutor::new();
+/// mock.returns_rows(vec![vec![Value::I64(3)]]);
+/// let user = futures_executor::block_on(find_user(&mut mock, 3))?;
+/// assert_eq!(user, Some(vec![Value::I64(3)]));
+/// # Ok::<(), ExecuteError>(())
+/// ```
+pub trait Executor: Send {
+    /// Run `query`, returning the number of rows affected.
+    fn execute<S, A>(
+        &mut self,
+        query: &Query<S, A>,
+    ) -> impl Future<Output = Result<u64, ExecuteError>> + Send
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync;
+
+    /// Run `query`, returning its rows as they arrive.
+    fn stream<'a, S, A>(
+        &'a mut self,
+        query: &'a Query<S, A>,
+    ) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync;
+
+    /// Run `query`, returning all of its rows.
+    fn fetch_all<S, A>(
+        &mut self,
+        query: &Query<S, A>,
+    ) -> impl Future<Output = Result<Vec<Vec<Value>>, ExecuteError>> + Send
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        async move { self.stream(query).try_collect().await }
+    }
+
+    /// Run `query`, returning its first row if there is one.
+    fn fetch_optional<S, A>(
+        &mut self,
+        query: &Query<S, A>,
+    ) -> impl Future<Output = Result<Option<Vec<Value>>, ExecuteError>> + Send
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        async move {
+            let mut rows = pin!(self.stream(query));
+            rows.next().await.transpose()
+        }
+    }
+
+    /// Run `query`, returning its first row, or [`ExecuteError::RowNotFound`].
+    fn fetch_one<S, A>(
+        &mut self,
+        query: &Query<S, A>,
+    ) -> impl Future<Output = Result<Vec<Value>, ExecuteError>> + Send
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        async move {
+            self.fetch_optional(query)
+                .await?
+                .ok_or(ExecuteError::RowNotFound)
+        }
+    }
+}
+
+/// Run `execute` for `query`, in its execution span when tracing.
+pub(crate) async fn traced_execute<S, A, F>(
+    query: &Query<S, A>,
+    execute: F,
+) -> Result<u64, ExecuteError>
+where
+    F: Future<Output = Result<u64, ExecuteError>>,
+{
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+
+        let execution = query.execution();
+        let result = execute.instrument(execution.span().clone()).await;
+        match &result {
+            Ok(affected) => execution.finish(*affected),
+            Err(error) => execution.fail(error),
+        }
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = query;
+        execute.await
+    }
+}
+
+/// Stream `rows` for `query`, in its execution span when tracing. The rows
+/// returned are recorded when the stream ends or is dropped.
+pub(crate) fn traced_stream<'a, S, A, R>(
+    query: &'a Query<S, A>,
+    rows: R,
+) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+where
+    R: Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a,
+{
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+
+        let state = (Box::pin(rows), Traced::new(query.execution()));
+        stream::unfold(state, |(mut rows, mut traced)| async move {
+            let row = match &traced.execution {
+                Some(execution) => rows.next().instrument(execution.span().clone()).await,
+                None => rows.next().await,
+            };
+            match &row {
+                Some(Ok(_)) => traced.rows += 1,
+                Some(Err(error)) => traced.fail(error),
+                None => traced.finish(),
+            }
+            Some((row?, (rows, traced)))
+        })
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = query;
+        rows
+    }
+}
+
+/// The execution of a streamed query, finished when dropped if it hasn't failed.
+#[cfg(feature = "tracing")]
+struct Traced {
+    execution: Option<crate::Execution>,
+    rows: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl Traced {
+    fn new(execution: crate::Execution) -> Self {
+        Traced {
+            execution: Some(execution),
+            rows: 0,
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(execution) = self.execution.take() {
+            execution.finish(self.rows);
+        }
+    }
+
+    fn fail(&mut self, error: &ExecuteError) {
+        if let Some(execution) = self.execution.take() {
+            execution.fail(error);
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Traced {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// The rows `rows` resolves to, for backends that fetch them all at once.
+pub(crate) fn all_at_once<'a, F>(
+    rows: F,
+) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+where
+    F: Future<Output = Result<Vec<Vec<Value>>, ExecuteError>> + Send + 'a,
+{
+    stream::once(rows)
+        .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+impl Executor for MockExecutor {
+    async fn execute<S, A>(&mut self, query: &Query<S, A>) -> Result<u64, ExecuteError>
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_execute(query, async move { Ok(self.run(query)?.affected) }).await
+    }
+
+    fn stream<'a, S, A>(
+        &'a mut self,
+        query: &'a Query<S, A>,
+    ) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_stream(query, all_at_once(async move { Ok(self.run(query)?.rows) }))
+    }
+}
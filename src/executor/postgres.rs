@@ -0,0 +1,219 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use bytes::BytesMut;
+use futures_core::Stream;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use tokio_postgres::{Client, Row};
+
+use super::{traced_execute, traced_stream, ExecuteError, Executor};
+use crate::{Query, ReadArguments, Value, ValueRef};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A bound value, converted to the type the server inferred for its parameter
+/// where that loses nothing.
+#[derive(Debug)]
+struct Param<'a>(ValueRef<'a>);
+
+impl ToSql for Param<'_> {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        match self.0 {
+            ValueRef::Null => Ok(IsNull::Yes),
+            ValueRef::Bool(value) => value.to_sql_checked(ty, out),
+            ValueRef::I16(value) => integer(value.into(), ty, out),
+            ValueRef::I32(value) => integer(value.into(), ty, out),
+            ValueRef::I64(value) => integer(value, ty, out),
+            ValueRef::U64(value) => integer(i64::try_from(value)?, ty, out),
+            ValueRef::F32(value) if *ty == Type::FLOAT4 => value.to_sql(ty, out),
+            ValueRef::F32(value) => f64::from(value).to_sql_checked(ty, out),
+            ValueRef::F64(value) => value.to_sql_checked(ty, out),
+            ValueRef::Text(value) => value.to_sql_checked(ty, out),
+            ValueRef::Bytes(value) => value.to_sql_checked(ty, out),
+            ValueRef::Custom(value) => Param(value.fallback()).to_sql(ty, out),
+        }
+    }
+
+    /// Checked by `to_sql` instead, which knows the value.
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn integer(value: i64, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+    match *ty {
+        Type::INT2 => i16::try_from(value)?.to_sql(ty, out),
+        Type::INT4 => i32::try_from(value)?.to_sql(ty, out),
+        _ => value.to_sql_checked(ty, out),
+    }
+}
+
+fn params<A: ReadArguments>(args: &A) -> Vec<Param<'_>> {
+    args.values().map(Param).collect()
+}
+
+/// A column value, read as the [`Value`] of its type. Columns of other types
+/// than those of `Value` are read as text, which only works for text-like types
+/// such as `varchar`; cast others in the SQL.
+#[derive(Debug)]
+struct Column(Value);
+
+impl<'a> FromSql<'a> for Column {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        Ok(Column(match *ty {
+            Type::BOOL => Value::Bool(FromSql::from_sql(ty, raw)?),
+            Type::INT2 => Value::I16(FromSql::from_sql(ty, raw)?),
+            Type::INT4 => Value::I32(FromSql::from_sql(ty, raw)?),
+            Type::INT8 => Value::I64(FromSql::from_sql(ty, raw)?),
+            Type::FLOAT4 => Value::F32(FromSql::from_sql(ty, raw)?),
+            Type::FLOAT8 => Value::F64(FromSql::from_sql(ty, raw)?),
+            Type::BYTEA => Value::Bytes(FromSql::from_sql(ty, raw)?),
+            _ if <String as FromSql>::accepts(ty) => Value::Text(FromSql::from_sql(ty, raw)?),
+            _ => {
+                return Err(
+                    format!("can't read a column of type {}, cast it in the SQL", ty).into(),
+                )
+            }
+        }))
+    }
+
+    fn from_sql_null(_ty: &Type) -> Result<Self, BoxError> {
+        Ok(Column(Value::Null))
+    }
+
+    /// Checked by `from_sql` instead, which falls back to text.
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+}
+
+fn decode(row: &Row) -> Result<Vec<Value>, ExecuteError> {
+    (0..row.len())
+        .map(|index| {
+            row.try_get::<_, Column>(index)
+                .map(|column| column.0)
+                .map_err(ExecuteError::backend)
+        })
+        .collect()
+}
+
+/// Runs queries rendered with `#![dialect(Postgres)]`, binding each value as the
+/// type the server inferred for its parameter.
+impl Executor for Client {
+    async fn execute<S, A>(&mut self, query: &Query<S, A>) -> Result<u64, ExecuteError>
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_execute(query, async move {
+            self.execute_raw(query.sql.as_ref(), params(&query.args))
+                .await
+                .map_err(ExecuteError::backend)
+        })
+        .await
+    }
+
+    fn stream<'a, S, A>(
+        &'a mut self,
+        query: &'a Query<S, A>,
+    ) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_stream(
+            query,
+            stream::once(self.query_raw(query.sql.as_ref(), params(&query.args)))
+                .map_err(ExecuteError::backend)
+                .map_ok(|rows| rows.map(|row| decode(&row.map_err(ExecuteError::backend)?)))
+                .try_flatten(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_sql(value: ValueRef<'_>, ty: &Type) -> Result<Option<Vec<u8>>, BoxError> {
+        let mut out = BytesMut::new();
+        Ok(match Param(value).to_sql(ty, &mut out)? {
+            IsNull::Yes => None,
+            IsNull::No => Some(out.to_vec()),
+        })
+    }
+
+    fn from_sql(ty: &Type, raw: Option<&[u8]>) -> Result<Value, BoxError> {
+        Column::from_sql_nullable(ty, raw).map(|column| column.0)
+    }
+
+    #[test]
+    fn narrows_integers_to_the_parameter_type() {
+        let cases = [
+            (ValueRef::I64(7), Type::INT2, &[0, 7][..]),
+            (ValueRef::I16(7), Type::INT4, &[0, 0, 0, 7]),
+            (ValueRef::I32(7), Type::INT8, &[0, 0, 0, 0, 0, 0, 0, 7]),
+            (ValueRef::U64(7), Type::INT4, &[0, 0, 0, 7]),
+        ];
+        for (value, ty, bytes) in cases {
+            assert_eq!(to_sql(value, &ty).unwrap().as_deref(), Some(bytes));
+        }
+    }
+
+    #[test]
+    fn rejects_integers_that_dont_fit() {
+        assert!(to_sql(ValueRef::I32(40_000), &Type::INT2).is_err());
+        assert!(to_sql(ValueRef::I64(1 << 31), &Type::INT4).is_err());
+        assert!(to_sql(ValueRef::U64(i64::MAX as u64 + 1), &Type::INT8).is_err());
+        assert!(to_sql(ValueRef::I64(7), &Type::TEXT).is_err());
+    }
+
+    #[test]
+    fn converts_floats_text_and_null() {
+        assert_eq!(
+            to_sql(ValueRef::F32(1.5), &Type::FLOAT4).unwrap(),
+            Some(1.5_f32.to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            to_sql(ValueRef::F32(1.5), &Type::FLOAT8).unwrap(),
+            Some(1.5_f64.to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            to_sql(ValueRef::Text("kim"), &Type::TEXT).unwrap(),
+            Some(b"kim".to_vec())
+        );
+        assert_eq!(to_sql(ValueRef::Null, &Type::INT4).unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_columns() {
+        assert_eq!(
+            from_sql(&Type::INT4, Some(&[0, 0, 1, 0])).unwrap(),
+            Value::I32(256)
+        );
+        assert_eq!(
+            from_sql(&Type::FLOAT8, Some(&2.5_f64.to_be_bytes())).unwrap(),
+            Value::F64(2.5)
+        );
+        assert_eq!(from_sql(&Type::INT8, None).unwrap(), Value::Null);
+        assert_eq!(from_sql(&Type::TEXT, None).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn decodes_other_text_like_columns_as_text() {
+        assert_eq!(
+            from_sql(&Type::VARCHAR, Some(b"kim")).unwrap(),
+            Value::Text("kim".into())
+        );
+        assert_eq!(
+            from_sql(&Type::NAME, Some(b"users")).unwrap(),
+            Value::Text("users".into())
+        );
+        assert!(from_sql(&Type::NUMERIC, Some(&[0, 0])).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use futures_core::Stream;
+use rusqlite::types::{Value as SqliteValue, ValueRef as SqliteValueRef};
+use rusqlite::{params_from_iter, Connection};
+
+use super::{all_at_once, traced_execute, traced_stream, ExecuteError, Executor};
+use crate::{Query, ReadArguments, Value, ValueRef};
+
+fn param(value: ValueRef<'_>) -> Result<SqliteValue, ExecuteError> {
+    Ok(match value {
+        ValueRef::Null => SqliteValue::Null,
+        ValueRef::Bool(value) => SqliteValue::Integer(value.into()),
+        ValueRef::I16(value) => SqliteValue::Integer(value.into()),
+        ValueRef::I32(value) => SqliteValue::Integer(value.into()),
+        ValueRef::I64(value) => SqliteValue::Integer(value),
+        ValueRef::U64(value) => match i64::try_from(value) {
+            Ok(value) => SqliteValue::Integer(value),
+            Err(_) => return Err(ExecuteError::UnsupportedValue(Value::U64(value))),
+        },
+        ValueRef::F32(value) => SqliteValue::Real(value.into()),
+        ValueRef::F64(value) => SqliteValue::Real(value),
+        ValueRef::Text(value) => SqliteValue::Text(value.into()),
+        ValueRef::Bytes(value) => SqliteValue::Blob(value.into()),
+        ValueRef::Custom(value) => return param(value.fallback()),
+    })
+}
+
+fn params<A: ReadArguments>(args: &A) -> Result<Vec<SqliteValue>, ExecuteError> {
+    args.values().map(param).collect()
+}
+
+fn decode(value: SqliteValueRef<'_>) -> Result<Value, ExecuteError> {
+    Ok(match value {
+        SqliteValueRef::Null => Value::Null,
+        SqliteValueRef::Integer(value) => Value::I64(value),
+        SqliteValueRef::Real(value) => Value::F64(value),
+        SqliteValueRef::Text(value) => Value::Text(
+            core::str::from_utf8(value)
+                .map_err(ExecuteError::backend)?
+                .into(),
+        ),
+        SqliteValueRef::Blob(value) => Value::Bytes(value.into()),
+    })
+}
+
+/// Runs queries rendered with `#![dialect(Sqlite)]`. SQLite runs them on the
+/// calling thread, so the futures are ready when first polled, and rows are
+/// streamed once all have been read.
+impl Executor for Connection {
+    async fn execute<S, A>(&mut self, query: &Query<S, A>) -> Result<u64, ExecuteError>
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_execute(query, async move {
+            let params = params(&query.args)?;
+            let affected = Connection::execute(self, query.sql.as_ref(), params_from_iter(params))
+                .map_err(ExecuteError::backend)?;
+            Ok(affected as u64)
+        })
+        .await
+    }
+
+    fn stream<'a, S, A>(
+        &'a mut self,
+        query: &'a Query<S, A>,
+    ) -> impl Stream<Item = Result<Vec<Value>, ExecuteError>> + Send + 'a
+    where
+        S: AsRef<str> + Sync,
+        A: ReadArguments + Sync,
+    {
+        traced_stream(
+            query,
+            all_at_once(async move {
+                let params = params(&query.args)?;
+                let mut statement = self
+                    .prepare(query.sql.as_ref())
+                    .map_err(ExecuteError::backend)?;
+                let columns = statement.column_count();
+                let mut rows = statement
+                    .query(params_from_iter(params))
+                    .map_err(ExecuteError::backend)?;
+                let mut all = Vec::new();
+                while let Some(row) = rows.next().map_err(ExecuteError::backend)? {
+                    let row = (0..columns)
+                        .map(|index| decode(row.get_ref(index).map_err(ExecuteError::backend)?))
+                        .collect::<Result<_, _>>()?;
+                    all.push(row);
+                }
+                Ok(all)
+            }),
+        )
+    }
+}
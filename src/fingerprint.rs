@@ -0,0 +1,52 @@
+use core::fmt;
+
+/// A stable hash of a query's shape, for grouping metrics and logs per statement.
+///
+/// It covers the source file of the `build_query!` invocation and the SQL of the
+/// branches taken, but not bound values, line numbers, whitespace or keyword
+/// case, and an IN list or batch counts the same however many items it has.
+/// Two invocations in the same file rendering the same SQL share a fingerprint
+/// unless given different names with `#![fingerprint("name")]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint(pub u64);
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0100_0000_01b3;
+
+impl Fingerprint {
+    /// The fingerprint of a builder nothing has been folded into.
+    pub const EMPTY: Fingerprint = Fingerprint(OFFSET_BASIS);
+
+    /// 64-bit FNV-1a of `bytes`, which `build_query!` uses for the source file
+    /// and, at compile time, for the SQL of each block.
+    pub const fn hash(bytes: &[u8]) -> u64 {
+        let mut hash = OFFSET_BASIS;
+        let mut index = 0;
+        while index < bytes.len() {
+            hash ^= bytes[index] as u64;
+            hash = hash.wrapping_mul(PRIME);
+            index += 1;
+        }
+        hash
+    }
+
+    /// Mix in the hash of one more piece of the query.
+    #[must_use]
+    pub const fn fold(self, hash: u64) -> Fingerprint {
+        let bytes = hash.to_le_bytes();
+        let mut state = self.0;
+        let mut index = 0;
+        while index < bytes.len() {
+            state ^= bytes[index] as u64;
+            state = state.wrapping_mul(PRIME);
+            index += 1;
+        }
+        Fingerprint(state)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
@@ -0,0 +1,109 @@
+use core::fmt;
+use core::ops::Range;
+
+#[cfg(feature = "fragments")]
+use alloc::vec::Vec;
+
+/// A position in the Rust source. `column` is 1-based, like `column!()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A branch taken on the way to a fragment: `if`, `else if`, `else` or a batch `for`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BranchStep {
+    pub keyword: &'static str,
+    pub line: u32,
+}
+
+/// Where a literal or bind of a `build_query!` invocation is in the Rust source.
+///
+/// `build_query!` emits one of these as a `'static` for every literal and bind.
+#[derive(Debug)]
+pub struct Origin {
+    pub location: Location,
+    /// The `build_query!` invocation itself.
+    pub call_site: Location,
+    /// `local_index` of each node from the root down to the block the fragment is in.
+    pub path: &'static [u16],
+    /// The branches leading to the fragment, outermost first.
+    pub branches: &'static [BranchStep],
+    pub kind: FragmentKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentKind {
+    /// The `index`th string literal of the invocation, in source order.
+    Literal(usize),
+    /// The placeholder of the `index`th bind of the invocation, in source order.
+    Bind(usize),
+}
+
+/// A byte range of rendered SQL and what produced it.
+#[derive(Debug)]
+pub struct Fragment {
+    pub range: Range<usize>,
+    /// `None` for SQL pushed by hand or by the builder itself, like `IN` list parentheses.
+    pub origin: Option<&'static Origin>,
+}
+
+/// The fragments making up a query's SQL, in rendering order.
+#[cfg(feature = "fragments")]
+#[derive(Debug, Default)]
+pub struct FragmentMap {
+    fragments: Vec<Fragment>,
+}
+
+#[cfg(feature = "fragments")]
+impl FragmentMap {
+    pub(crate) fn push(&mut self, range: Range<usize>, origin: Option<&'static Origin>) {
+        if !range.is_empty() {
+            self.fragments.push(Fragment { range, origin });
+        }
+    }
+
+    /// The fragment containing the byte at `offset`.
+    pub fn at(&self, offset: usize) -> Option<&Fragment> {
+        let index = self
+            .fragments
+            .partition_point(|fragment| fragment.range.end <= offset);
+        self.fragments
+            .get(index)
+            .filter(|fragment| fragment.range.contains(&offset))
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Fragment> {
+        self.fragments.iter()
+    }
+}
+
+/// The source of the SQL at some position, as found by `Query::locate`.
+#[derive(Debug)]
+pub struct SourceSpan<'q> {
+    pub origin: &'static Origin,
+    /// The rendered SQL of the fragment containing the position.
+    pub sql: &'q str,
+}
+
+impl fmt::Display for SourceSpan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.sql)?;
+        if let Some(branch) = self.origin.branches.last() {
+            write!(f, " in the `{}` branch", branch.keyword)?;
+        }
+        write!(
+            f,
+            " at {} (build_query! at {})",
+            self.origin.location, self.origin.call_site
+        )
+    }
+}
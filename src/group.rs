@@ -0,0 +1,143 @@
+use crate::{BuildError, Fingerprint};
+
+/// How deeply groups can nest in one `build_query!`.
+pub const MAX_GROUP_DEPTH: usize = 8;
+
+/// A group of items joined at runtime, depending on which of them render anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    /// `where { ... }`: ` WHERE a AND b`, or nothing.
+    Where,
+    /// `or { ... }`: `(a OR b)`, or nothing.
+    Or,
+    /// `set { ... }`: ` SET a, b`, failing with [`BuildError::EmptySet`] if empty.
+    Set,
+}
+
+impl GroupKind {
+    fn open(self) -> &'static str {
+        match self {
+            GroupKind::Where => " WHERE ",
+            GroupKind::Or => "(",
+            GroupKind::Set => " SET ",
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self {
+            GroupKind::Where => " AND ",
+            GroupKind::Or => " OR ",
+            GroupKind::Set => ", ",
+        }
+    }
+
+    fn close(self) -> &'static str {
+        match self {
+            GroupKind::Where => "",
+            GroupKind::Or => ")",
+            GroupKind::Set => "",
+        }
+    }
+
+    fn hash(self) -> u64 {
+        const WHERE: u64 = Fingerprint::hash(b"where");
+        const OR: u64 = Fingerprint::hash(b"or");
+        const SET: u64 = Fingerprint::hash(b"set");
+        match self {
+            GroupKind::Where => WHERE,
+            GroupKind::Or => OR,
+            GroupKind::Set => SET,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    kind: GroupKind,
+    /// Whether anything of the group has been rendered yet.
+    opened: bool,
+    /// Whether the current item has yet to render anything.
+    pending: bool,
+}
+
+/// The groups a builder is inside of, innermost last.
+pub(crate) struct Groups {
+    frames: [Frame; MAX_GROUP_DEPTH],
+    depth: usize,
+    pending: bool,
+}
+
+impl Default for Groups {
+    fn default() -> Self {
+        Groups {
+            frames: [Frame {
+                kind: GroupKind::Where,
+                opened: false,
+                pending: false,
+            }; MAX_GROUP_DEPTH],
+            depth: 0,
+            pending: false,
+        }
+    }
+}
+
+impl Groups {
+    pub(crate) fn open(&mut self, kind: GroupKind) {
+        assert!(
+            self.depth < MAX_GROUP_DEPTH,
+            "groups nested deeper than {}",
+            MAX_GROUP_DEPTH
+        );
+        self.frames[self.depth] = Frame {
+            kind,
+            opened: false,
+            pending: false,
+        };
+        self.depth += 1;
+    }
+
+    pub(crate) fn next_item(&mut self) {
+        self.frames[self.depth - 1].pending = true;
+        self.pending = true;
+    }
+
+    /// Leave the innermost group, returning the SQL closing it if it was opened.
+    pub(crate) fn close(&mut self) -> Result<Option<&'static str>, BuildError> {
+        self.depth -= 1;
+        let frame = self.frames[self.depth];
+        if frame.pending {
+            self.pending = self.frames[..self.depth].iter().any(|frame| frame.pending);
+        }
+        if frame.opened {
+            Ok(Some(frame.kind.close()))
+        } else if frame.kind == GroupKind::Set {
+            Err(BuildError::EmptySet)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The next SQL owed, outermost first, by groups whose current item is
+    /// about to render, with the hash to fold into the fingerprint if any.
+    pub(crate) fn next_pending(&mut self) -> Option<(&'static str, Option<u64>)> {
+        if !self.pending {
+            return None;
+        }
+        let frames = &mut self.frames[..self.depth];
+        match frames.iter_mut().find(|frame| frame.pending) {
+            Some(frame) => {
+                frame.pending = false;
+                if frame.opened {
+                    Some((frame.kind.separator(), None))
+                } else {
+                    frame.opened = true;
+                    Some((frame.kind.open(), Some(frame.kind.hash())))
+                }
+            }
+            None => {
+                self.pending = false;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+
+use crate::{Arguments, Builder, Dialect, Direction, Encode, Fingerprint, OrderBy, Sink, Splice};
+
+/// A column a [`Keyset`] is ordered by, made with [`sort_key!`](crate::sort_key)
+/// from a string literal, as the column is rendered into the query as it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    column: &'static str,
+    direction: Direction,
+}
+
+impl SortKey {
+    #[doc(hidden)]
+    pub const fn __new(column: &'static str, direction: Direction) -> Self {
+        SortKey { column, direction }
+    }
+}
+
+/// A [`SortKey`] sorting the column in a string literal `asc` or `desc`:
+///
+/// ```
+/// # use sql_builder_test::{sort_key, OrderBy, SortKey};
+/// const NEWEST: &[SortKey] = &[sort_key!(desc "created_at"), sort_key!(asc "id")];
+/// assert_eq!(NEWEST[0].column(), "created_at");
+/// ```
+#[macro_export]
+macro_rules! sort_key {
+    (asc $column:literal) => {
+        $crate::SortKey::__new($column, $crate::Direction::Asc)
+    };
+    (desc $column:literal) => {
+        $crate::SortKey::__new($column, $crate::Direction::Desc)
+    };
+}
+
+impl crate::__private::Sealed for SortKey {}
+
+impl OrderBy for SortKey {
+    fn column(&self) -> &'static str {
+        self.column
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+}
+
+/// A value of a sort key, written into cursors as text.
+pub trait CursorValue: Encode + Sized {
+    fn write_cursor(&self, out: &mut String);
+
+    fn read_cursor(text: &str) -> Option<Self>;
+}
+
+macro_rules! cursor_value {
+    ($($ty:ty),*) => {$(
+        impl CursorValue for $ty {
+            fn write_cursor(&self, out: &mut String) {
+                // Writing to a `String` can't fail.
+                let _ = write!(out, "{}", self);
+            }
+
+            fn read_cursor(text: &str) -> Option<Self> {
+                text.parse().ok()
+            }
+        }
+    )*};
+}
+
+cursor_value!(i16, i32, i64, u16, u32, u64, bool);
+
+impl CursorValue for String {
+    fn write_cursor(&self, out: &mut String) {
+        out.push_str(self);
+    }
+
+    fn read_cursor(text: &str) -> Option<Self> {
+        Some(text.into())
+    }
+}
+
+/// The values of all sort keys of a row, as a tuple in the order of the keys.
+pub trait KeyValues: Sized {
+    const LEN: usize;
+
+    /// Render a placeholder for the value at `index`, binding it.
+    fn push_value<S: Sink, D: Dialect, A: Arguments>(
+        &self,
+        index: usize,
+        builder: &mut Builder<S, D, A>,
+    );
+
+    /// Append the text of the value at `index` to `out`.
+    fn write_value(&self, index: usize, out: &mut String);
+
+    /// The values from their texts, `LEN` of them.
+    fn read_values<'a, I: Iterator<Item = &'a str>>(texts: I) -> Option<Self>;
+}
+
+macro_rules! key_values {
+    ($len:expr; $($index:tt $name:ident),*) => {
+        impl<$($name: CursorValue),*> KeyValues for ($($name,)*) {
+            const LEN: usize = $len;
+
+            fn push_value<S: Sink, D: Dialect, A: Arguments>(
+                &self,
+                index: usize,
+                builder: &mut Builder<S, D, A>,
+            ) {
+                builder.push_sql_arg();
+                match index {
+                    $($index => builder.push_bind_arg(&self.$index),)*
+                    _ => unreachable!("key index out of range"),
+                }
+            }
+
+            fn write_value(&self, index: usize, out: &mut String) {
+                match index {
+                    $($index => self.$index.write_cursor(out),)*
+                    _ => unreachable!("key index out of range"),
+                }
+            }
+
+            fn read_values<'a, I: Iterator<Item = &'a str>>(mut texts: I) -> Option<Self> {
+                Some(($($name::read_cursor(texts.next()?)?,)*))
+            }
+        }
+    };
+}
+
+key_values!(1; 0 K0);
+key_values!(2; 0 K0, 1 K1);
+key_values!(3; 0 K0, 1 K1, 2 K2);
+key_values!(4; 0 K0, 1 K1, 2 K2, 3 K3);
+
+/// A cursor that wasn't made by [`Keyset::cursor`] for the same sort keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorError;
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed pagination cursor")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CursorError {}
+
+/// Keyset pagination: the page of `limit` rows after the row a cursor was taken
+/// from, in the order of `keys`. The keys must identify a row, e.g. by ending
+/// with the primary key, and must not be nullable.
+///
+/// `predicate` selects the rows after the cursor and `order_by` renders the
+/// `ORDER BY` and `LIMIT`:
+///
+/// ```
+/// # use sql_builder_test::{build_query, sort_key, Keyset, Postgres, SortKey};
+/// # fn main() -> Result<(), sql_builder_test::CursorError> {
+/// # let (author, cursor) = (7, None);
+/// const KEYS: &[SortKey] = &[sort_key!(desc "created_at"), sort_key!(asc "id")];
+///
+/// let page = Keyset::<(i64, i64)>::new(KEYS, 50).after(cursor)?;
+/// let query = build_query!(
+///     #![dialect(Postgres)]
+///     "SELECT id, created_at FROM posts"
+///     where { "author_id = " author ..page.predicate() }
+///     ..page.order_by()
+/// );
+/// assert_eq!(
+///     query.sql,
+///     "SELECT id, created_at FROM posts WHERE author_id = $1 ORDER BY created_at DESC, id ASC LIMIT $2"
+/// );
+///
+/// // ... and for the next page, from the last row of this one:
+/// # let last = (1_700_000_000, 42);
+/// let next = page.cursor(&last);
+/// let page = Keyset::<(i64, i64)>::new(KEYS, 50).after(Some(&next))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keyset<K> {
+    keys: &'static [SortKey],
+    after: Option<K>,
+    limit: u64,
+}
+
+impl<K: KeyValues> Keyset<K> {
+    /// The first page. Panics unless there is one key per value of `K`.
+    pub fn new(keys: &'static [SortKey], limit: u64) -> Self {
+        assert!(
+            !keys.is_empty() && keys.len() == K::LEN,
+            "keyset of {} sort keys for {} values",
+            keys.len(),
+            K::LEN
+        );
+        Keyset {
+            keys,
+            after: None,
+            limit,
+        }
+    }
+
+    /// The page after the row `cursor` was taken from, or the first page without one.
+    pub fn after(self, cursor: Option<&str>) -> Result<Self, CursorError> {
+        match cursor {
+            None => Ok(self),
+            Some(cursor) => {
+                let mut texts = alloc::vec::Vec::with_capacity(K::LEN);
+                for part in cursor.split('.') {
+                    texts.push(decode_hex(part).ok_or(CursorError)?);
+                }
+                if texts.len() != K::LEN {
+                    return Err(CursorError);
+                }
+                let after = K::read_values(texts.iter().map(String::as_str)).ok_or(CursorError)?;
+                Ok(self.after_keys(after))
+            }
+        }
+    }
+
+    /// The page after the row with the sort key values `after`.
+    pub fn after_keys(mut self, after: K) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// The opaque cursor for the page after the row with the sort key values `last`.
+    pub fn cursor(&self, last: &K) -> String {
+        let mut cursor = String::new();
+        let mut text = String::new();
+        for index in 0..K::LEN {
+            if index > 0 {
+                cursor.push('.');
+            }
+            text.clear();
+            last.write_value(index, &mut text);
+            for byte in text.bytes() {
+                let _ = write!(cursor, "{:02x}", byte);
+            }
+        }
+        cursor
+    }
+
+    /// The rows after the cursor: `(a, b) > ($1, $2)` if the dialect has row
+    /// values and all keys sort the same way, else `(a > $1 OR (a = $2 AND b > $3))`.
+    /// Renders nothing on the first page.
+    pub fn predicate(&self) -> KeysetPredicate<'_, K> {
+        KeysetPredicate { keyset: self }
+    }
+
+    /// ` ORDER BY a ASC, b ASC LIMIT $n`, binding the limit.
+    pub fn order_by(&self) -> KeysetOrder<'_, K> {
+        KeysetOrder { keyset: self }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<String> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|start| u8::from_str_radix(&hex[start..start + 2], 16).ok())
+        .collect::<Option<_>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// What [`Keyset::predicate`] splices in.
+pub struct KeysetPredicate<'a, K> {
+    keyset: &'a Keyset<K>,
+}
+
+impl<K: KeyValues, A: Arguments> Splice<A> for KeysetPredicate<'_, K> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        const AFTER: u64 = Fingerprint::hash(b"keyset after");
+        const ROW_VALUES: u64 = Fingerprint::hash(b"row values");
+
+        let keys = self.keyset.keys;
+        let after = match &self.keyset.after {
+            Some(after) => after,
+            None => return,
+        };
+        builder.fold_fingerprint(AFTER);
+        fold_keys(builder, keys);
+
+        let direction = keys[0].direction;
+        if keys.len() == 1 {
+            builder.push_sql(keys[0].column);
+            builder.push_sql(direction.after());
+            after.push_value(0, builder);
+        } else if D::ROW_VALUES && keys.iter().all(|key| key.direction == direction) {
+            builder.fold_fingerprint(ROW_VALUES);
+            builder.push_sql("(");
+            for (index, key) in keys.iter().enumerate() {
+                if index > 0 {
+                    builder.push_sql(", ");
+                }
+                builder.push_sql(key.column);
+            }
+            builder.push_sql(")");
+            builder.push_sql(direction.after());
+            builder.push_sql("(");
+            for index in 0..keys.len() {
+                if index > 0 {
+                    builder.push_sql(", ");
+                }
+                after.push_value(index, builder);
+            }
+            builder.push_sql(")");
+        } else {
+            // Rows after on the first key, or tied on it and after on the next, and so on.
+            builder.push_sql("(");
+            for (last, key) in keys.iter().enumerate() {
+                if last > 0 {
+                    builder.push_sql(" OR (");
+                }
+                for (index, tied) in keys[..last].iter().enumerate() {
+                    builder.push_sql(tied.column);
+                    builder.push_sql(" = ");
+                    after.push_value(index, builder);
+                    builder.push_sql(" AND ");
+                }
+                builder.push_sql(key.column);
+                builder.push_sql(key.direction.after());
+                after.push_value(last, builder);
+                if last > 0 {
+                    builder.push_sql(")");
+                }
+            }
+            builder.push_sql(")");
+        }
+    }
+}
+
+/// What [`Keyset::order_by`] splices in.
+pub struct KeysetOrder<'a, K> {
+    keyset: &'a Keyset<K>,
+}
+
+impl<K: KeyValues, A: Arguments> Splice<A> for KeysetOrder<'_, K> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        const LIMIT: u64 = Fingerprint::hash(b" limit ");
+
+        builder.push_order_by();
+        for (index, key) in self.keyset.keys.iter().enumerate() {
+            if index > 0 {
+                builder.push_sql(", ");
+            }
+            builder.push_order_term(*key);
+        }
+        builder.fold_fingerprint(LIMIT);
+        builder.push_sql(" LIMIT ");
+        builder.push_sql_arg();
+        // LIMIT is a signed bigint, and no page is that long anyway.
+        builder.push_bind_arg(i64::try_from(self.keyset.limit).unwrap_or(i64::MAX));
+    }
+}
+
+fn fold_keys<S: Sink, D: Dialect, A: Arguments>(builder: &mut Builder<S, D, A>, keys: &[SortKey]) {
+    for key in keys {
+        builder.fold_fingerprint(Fingerprint::hash(key.column.as_bytes()));
+        builder.fold_fingerprint(Fingerprint::hash(key.direction.sql().as_bytes()));
+    }
+}
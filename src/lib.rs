@@ -1,56 +1,474 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub extern crate sql_builder_macros;
 
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use core::ops::Range;
+
 use proc_macro_hack::proc_macro_hack;
 
-pub struct Builder {
-    sql: String,
+mod args;
+mod derive;
+mod dialect;
+mod error;
+#[cfg(feature = "executor")]
+mod executor;
+mod fingerprint;
+mod fragments;
+mod group;
+#[cfg(feature = "alloc")]
+mod keyset;
+#[cfg(feature = "alloc")]
+mod mock;
+mod order;
+mod sink;
+#[cfg(feature = "alloc")]
+mod splice;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "alloc")]
+mod wire;
+
+pub use args::{Arguments, CustomValue, DefaultArgs, Encode, ReadArguments, ValueRef};
+#[cfg(feature = "alloc")]
+pub use args::{FromValue, Native, PackedArgs, PackedIter, Value, Values};
+#[cfg(feature = "alloc")]
+pub use derive::FilterPredicates;
+pub use derive::{SqlFilter, SqlInsert, SqlUpdate};
+pub use dialect::{Dialect, Generic, Postgres, Sqlite};
+pub use error::BuildError;
+#[cfg(feature = "executor")]
+pub use executor::{ExecuteError, Executor};
+pub use fingerprint::Fingerprint;
+#[cfg(feature = "fragments")]
+pub use fragments::FragmentMap;
+pub use fragments::{BranchStep, Fragment, FragmentKind, Location, Origin, SourceSpan};
+pub use group::{GroupKind, MAX_GROUP_DEPTH};
+#[cfg(feature = "alloc")]
+pub use keyset::{
+    CursorError, CursorValue, KeyValues, Keyset, KeysetOrder, KeysetPredicate, SortKey,
+};
+#[cfg(feature = "alloc")]
+pub use mock::{Executed, MockError, MockExecutor, MockOutcome};
+pub use order::{Direction, Nulls, OrderBy, ParseOrderError, Sort};
+pub use sink::{DefaultSink, FixedBuf, Sink, DEFAULT_FIXED_CAPACITY};
+#[cfg(feature = "alloc")]
+pub use splice::{Splice, SqlFragment};
+#[cfg(all(feature = "tracing", feature = "std"))]
+pub use trace::Execution;
+#[cfg(feature = "alloc")]
+pub use wire::{write_execute, write_sync, WireError};
+
+pub struct Builder<S = DefaultSink, D = Generic, A = DefaultArgs> {
+    sql: S,
+    args: A,
     args_count: usize,
     args_size: usize,
+    error: Option<BuildError>,
+    /// Whether an IN list rendered empty, which fails the build unless the
+    /// dialect allows it. Fragments leave that to the query they are spliced into.
+    empty_in_list: bool,
+    fingerprint: Fingerprint,
+    variant: u64,
+    groups: group::Groups,
+    len: usize,
+    /// Where the placeholders are, when they are tracked: for fragments, and for
+    /// queries with the `splice-queries` feature.
+    #[cfg(feature = "alloc")]
+    placeholders: Option<alloc::vec::Vec<Range<usize>>>,
+    #[cfg(feature = "fragments")]
+    fragments: FragmentMap,
+    dialect: PhantomData<D>,
 }
 
 impl Builder {
     pub fn new() -> Self {
+        Self::with_sink(DefaultSink::default())
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Sink, D: Dialect, A: Arguments> Builder<S, D, A> {
+    /// Render into `sink` instead of a fresh `String`.
+    ///
+    /// The sink is not cleared first, so anything already in it is kept as a prefix.
+    pub fn with_sink(sink: S) -> Self {
         Self {
-            sql: String::new(),
+            sql: sink,
+            args: A::default(),
             args_count: 0,
             args_size: 0,
+            error: None,
+            empty_in_list: false,
+            fingerprint: Fingerprint::EMPTY,
+            variant: Fingerprint::EMPTY.0,
+            groups: group::Groups::default(),
+            len: 0,
+            #[cfg(feature = "alloc")]
+            placeholders: if cfg!(feature = "splice-queries") {
+                Some(alloc::vec::Vec::new())
+            } else {
+                None
+            },
+            #[cfg(feature = "fragments")]
+            fragments: FragmentMap::default(),
+            dialect: PhantomData,
         }
     }
 
+    /// Remember the first error, to be reported when building.
+    fn fail(&mut self, error: BuildError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Mix `hash` into the fingerprint of the query being built.
+    #[inline]
+    pub fn fold_fingerprint(&mut self, hash: u64) {
+        self.fingerprint = self.fingerprint.fold(hash);
+    }
+
+    /// Mix the hash of a block's position and SQL into the variant id, which tells
+    /// apart the combinations of branches taken, and invocations from each other.
+    #[inline]
+    pub fn fold_variant(&mut self, hash: u64) {
+        self.variant = Fingerprint(self.variant).fold(hash).0;
+    }
+
     #[inline]
     pub fn push_sql(&mut self, sql: &'static str) {
-        use std::fmt::Write;
-        write!(self.sql, "{}", sql).unwrap();
+        self.push_sql_from(sql, None);
     }
 
     pub fn push_sql_arg(&mut self) {
-        use std::fmt::Write;
-        write!(self.sql, "${}", self.args_count).unwrap();
-        self.args_count += 1;
+        self.push_sql_arg_from(None);
     }
 
+    /// Like [`push_sql`](Self::push_sql), recording `origin` in the fragment map.
     #[inline]
-    pub fn push_bind_arg<T>(&mut self, arg: T)
+    pub fn push_sql_at(&mut self, sql: &'static str, origin: &'static Origin) {
+        self.push_sql_from(sql, Some(origin));
+    }
+
+    /// Like [`push_sql_arg`](Self::push_sql_arg), recording `origin` in the fragment map.
+    #[inline]
+    pub fn push_sql_arg_at(&mut self, origin: &'static Origin) {
+        self.push_sql_arg_from(Some(origin));
+    }
+
+    /// Start a group of items joined depending on which of them render anything.
+    pub fn open_group(&mut self, kind: GroupKind) {
+        self.groups.open(kind);
+    }
+
+    /// Start the next item of the innermost group.
+    pub fn next_group_item(&mut self) {
+        self.groups.next_item();
+    }
+
+    pub fn close_group(&mut self) {
+        match self.groups.close() {
+            Ok(Some(sql)) => self.write_sql(sql, None),
+            Ok(None) => {}
+            Err(error) => self.fail(error),
+        }
+    }
+
+    /// Render what the groups being rendered into owe before their current items.
+    fn open_groups(&mut self) {
+        while let Some((sql, hash)) = self.groups.next_pending() {
+            if let Some(hash) = hash {
+                self.fold_fingerprint(hash);
+            }
+            self.write_sql(sql, None);
+        }
+    }
+
+    fn push_sql_from(&mut self, sql: &str, origin: Option<&'static Origin>) {
+        if !sql.is_empty() {
+            self.open_groups();
+            self.write_sql(sql, origin);
+        }
+    }
+
+    #[cfg_attr(not(feature = "fragments"), allow(unused_variables))]
+    fn write_sql(&mut self, sql: &str, origin: Option<&'static Origin>) {
+        if self.sql.push_str(sql).is_err() {
+            self.fail(BuildError::SinkOverflow);
+        }
+        #[cfg(feature = "fragments")]
+        self.fragments.push(self.len..self.len + sql.len(), origin);
+        self.len += sql.len();
+    }
+
+    #[cfg_attr(not(feature = "fragments"), allow(unused_variables))]
+    fn push_sql_arg_from(&mut self, origin: Option<&'static Origin>) {
+        self.open_groups();
+        let mut out = sink::FmtSink {
+            sink: &mut self.sql,
+            len: 0,
+        };
+        let result = D::write_placeholder(self.args_count, &mut out);
+        let written = out.len;
+        if result.is_err() {
+            self.fail(BuildError::SinkOverflow);
+        }
+        #[cfg(feature = "fragments")]
+        self.fragments.push(self.len..self.len + written, origin);
+        #[cfg(feature = "alloc")]
+        if let Some(placeholders) = &mut self.placeholders {
+            placeholders.push(self.len..self.len + written);
+        }
+        self.len += written;
+        self.args_count += 1;
+    }
+
+    /// Render a query built elsewhere or a [`SqlFragment`], e.g. as a subquery,
+    /// with its placeholders numbered after the ones already rendered, and take
+    /// over its binds.
+    #[cfg(feature = "alloc")]
+    pub fn splice<T: Splice<A>>(&mut self, part: T) {
+        part.splice_into(self);
+    }
+
+    /// Render ` ORDER BY`, for the terms of an `order by` to follow.
+    pub fn push_order_by(&mut self) {
+        const ORDER_BY: u64 = Fingerprint::hash(b" order by ");
+
+        self.fold_fingerprint(ORDER_BY);
+        self.push_sql(" ORDER BY ");
+    }
+
+    /// Render the column of `term` with its direction and NULLs order. All of it
+    /// comes from `'static` strings of the [`OrderBy`] impl, never from input.
+    pub fn push_order_term<T: OrderBy>(&mut self, term: T) {
+        let column = term.column();
+        let direction = term.direction().sql();
+        self.fold_fingerprint(Fingerprint::hash(column.as_bytes()));
+        self.fold_fingerprint(Fingerprint::hash(direction.as_bytes()));
+        self.push_sql(column);
+        self.push_sql(direction);
+        if let Some(nulls) = term.nulls() {
+            self.fold_fingerprint(Fingerprint::hash(nulls.sql().as_bytes()));
+            self.push_sql(nulls.sql());
+        }
+    }
+
+    /// Render a parenthesized list with one placeholder per item, binding each item.
+    pub fn push_in_list<I>(&mut self, items: I)
     where
-        T: Sized,
+        I: IntoIterator,
+        I::Item: Encode,
     {
-        self.args_size += std::mem::size_of_val(&arg);
+        const IN_LIST: u64 = Fingerprint::hash(b"in (?)");
+
+        let mut empty = true;
+        self.fold_fingerprint(IN_LIST);
+        self.push_sql("(");
+        for item in items {
+            if !empty {
+                self.push_sql(", ");
+            }
+            empty = false;
+            self.push_sql_arg();
+            self.push_bind_arg(item);
+        }
+        self.push_sql(")");
+        self.empty_in_list |= empty;
     }
 
-    pub fn build(self) -> Query {
-        Query {
+    /// How many more rows of `row_params` parameters each fit in this statement,
+    /// keeping `reserved_params` free for what is rendered after the rows.
+    ///
+    /// Fails with [`BuildError::TooManyParameters`] if not even a single row fits.
+    ///
+    /// `build_query!` counts what follows a batch `for` when it expands, so IN
+    /// lists and splices can't follow one, not even in an `if`:
+    ///
+    /// ```compile_fail
+    /// # use sql_builder_test::build_query;
+    /// # let (rows, ids, upsert) = (vec![(1, 2)], vec![3], true);
+    /// let queries = build_query!(
+    ///     "INSERT INTO t (a, b) VALUES "
+    ///     for (a, b) in rows { "(" a ", " b ")" }
+    ///     if upsert { " ON CONFLICT (a) DO UPDATE SET b = 0 WHERE t.a IN " in ids }
+    /// );
+    /// ```
+    pub fn batch_capacity(
+        &self,
+        row_params: usize,
+        reserved_params: usize,
+    ) -> Result<usize, BuildError> {
+        if row_params == 0 {
+            return Ok(usize::MAX);
+        }
+        let available = D::MAX_PARAMS
+            .saturating_sub(self.args_count)
+            .saturating_sub(reserved_params);
+        match available / row_params {
+            0 => Err(BuildError::TooManyParameters {
+                count: self.args_count + row_params + reserved_params,
+                max: D::MAX_PARAMS,
+            }),
+            rows => Ok(rows),
+        }
+    }
+
+    /// Add `arg` to the arguments, for the placeholder just rendered.
+    #[inline]
+    pub fn push_bind_arg<T: Encode>(&mut self, arg: T) {
+        self.args_size += arg.encode().size();
+        self.args.add(&arg);
+    }
+
+    /// Panics on any [`BuildError`]; see [`try_build`](Self::try_build). `build_query!`
+    /// only builds this way when the errors left are dialect limits, sink overflow and
+    /// those spliced in: invocations with `in` lists or `set` groups that can be empty
+    /// use `try_build`.
+    pub fn build(self) -> Query<S, A> {
+        self.build_from(None)
+    }
+
+    pub fn try_build(self) -> Result<Query<S, A>, BuildError> {
+        self.try_build_from(None)
+    }
+
+    /// Like [`build`](Self::build), for the `build_query!` invocation at `call_site`.
+    pub fn build_at(self, call_site: &'static Location) -> Query<S, A> {
+        self.build_from(Some(call_site))
+    }
+
+    /// Like [`try_build`](Self::try_build), for the `build_query!` invocation at `call_site`.
+    pub fn try_build_at(self, call_site: &'static Location) -> Result<Query<S, A>, BuildError> {
+        self.try_build_from(Some(call_site))
+    }
+
+    fn build_from(self, call_site: Option<&'static Location>) -> Query<S, A> {
+        match self.try_build_from(call_site) {
+            Ok(query) => query,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn try_build_from(
+        self,
+        call_site: Option<&'static Location>,
+    ) -> Result<Query<S, A>, BuildError> {
+        let error = match self.error {
+            Some(error) => Some(error),
+            None if self.empty_in_list && !D::EMPTY_IN_LIST => Some(BuildError::EmptyInList),
+            None if self.args_count > D::MAX_PARAMS => Some(BuildError::TooManyParameters {
+                count: self.args_count,
+                max: D::MAX_PARAMS,
+            }),
+            None => None,
+        };
+        #[cfg(feature = "tracing")]
+        trace::built(trace::Built {
+            call_site,
+            variant: self.variant,
+            fingerprint: self.fingerprint,
+            args_count: self.args_count,
+            sql_len: self.len,
+            error: error.as_ref(),
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        Ok(Query {
             sql: self.sql,
+            args: self.args,
             args_count: self.args_count,
             args_size: self.args_size,
-        }
+            fingerprint: self.fingerprint,
+            #[cfg(feature = "splice-queries")]
+            placeholders: self.placeholders.unwrap_or_default(),
+            #[cfg(feature = "splice-queries")]
+            sql_len: self.len,
+            #[cfg(feature = "tracing")]
+            call_site,
+            variant: self.variant,
+            postgres_placeholders: dialect::numbers_like_postgres::<D>(),
+            #[cfg(feature = "fragments")]
+            fragments: self.fragments,
+        })
     }
 }
 
-pub struct Query {
-    pub sql: String,
+pub struct Query<S = DefaultSink, A = DefaultArgs> {
+    pub sql: S,
+    /// The bound values, in placeholder order.
+    pub args: A,
     pub args_count: usize,
+    /// The [size](ValueRef::size) of the bound values in bytes, summed.
     pub args_size: usize,
+    pub fingerprint: Fingerprint,
+    /// Byte ranges of the placeholders in `sql`, relative to where this query
+    /// started rendering into the sink.
+    #[cfg(feature = "splice-queries")]
+    pub placeholders: alloc::vec::Vec<Range<usize>>,
+    /// How much SQL this query rendered, at the end of `sql`.
+    #[cfg(feature = "splice-queries")]
+    pub sql_len: usize,
+    /// The `build_query!` invocation that built this, if any.
+    #[cfg(feature = "tracing")]
+    pub call_site: Option<&'static Location>,
+    /// Identifies the combination of branches taken within the invocation, seeded
+    /// like the fingerprint so that different invocations don't share ids.
+    pub variant: u64,
+    /// Whether the placeholders are numbered `$1`, `$2`, ..., as with the `Postgres`
+    /// dialect, so the query can be [written](Self::write_parse) for the wire.
+    pub postgres_placeholders: bool,
+    /// Byte ranges of `sql`, relative to where this query started rendering into the sink.
+    #[cfg(feature = "fragments")]
+    pub fragments: FragmentMap,
+}
+
+#[cfg(feature = "fragments")]
+impl<S: AsRef<str>, A> Query<S, A> {
+    /// Find what produced the SQL at a 1-based character `position`, as reported
+    /// by e.g. the `position` field of a Postgres error.
+    ///
+    /// Only meaningful when the sink was empty when the query started rendering.
+    pub fn locate(&self, position: usize) -> Option<SourceSpan<'_>> {
+        let sql = self.sql.as_ref();
+        let offset = sql.char_indices().nth(position.checked_sub(1)?)?.0;
+        let fragment = self.fragments.at(offset)?;
+        Some(SourceSpan {
+            origin: fragment.origin?,
+            sql: &sql[fragment.range.clone()],
+        })
+    }
 }
 
-#[proc_macro_hack]
+#[proc_macro_hack(support_nested)]
 pub use sql_builder_macros::build_query;
+
+#[cfg(feature = "alloc")]
+#[proc_macro_hack(support_nested)]
+pub use sql_builder_macros::sql_fragment;
+
+pub use sql_builder_macros::{OrderBy, SqlFilter, SqlInsert, SqlUpdate};
+
+/// For the macros only, and not part of the API: other uses aren't supported.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "alloc")]
+    pub use alloc::vec::Vec;
+
+    /// Implemented by `#[derive(OrderBy)]` alongside `OrderBy`, and by nothing
+    /// outside this crate otherwise. That is a convention the compiler can't
+    /// enforce, since the derive's output could as well be written by hand.
+    pub trait Sealed {}
+}
@@ -0,0 +1,184 @@
+//! An executor recording queries instead of running them, for unit tests.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Fingerprint, Query, ReadArguments, Value};
+
+/// A query run against a [`MockExecutor`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Executed {
+    pub sql: String,
+    pub fingerprint: Fingerprint,
+    pub variant: u64,
+    pub args: Vec<Value>,
+}
+
+/// What a [`MockExecutor`] returned for a query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockOutcome {
+    pub rows: Vec<Vec<Value>>,
+    /// The rows affected: the number of rows, unless scripted otherwise.
+    pub affected: u64,
+}
+
+/// An error scripted with [`MockExecutor::fails`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockError(pub String);
+
+impl fmt::Display for MockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MockError {}
+
+/// Records the queries run against it and answers them in turn with scripted rows
+/// or errors, or with no rows once the script runs out.
+///
+/// ```
+/// # use sql_builder_test::{MockError, MockExecutor, Value};
+/// # mod repo {
+/// #     use sql_builder_test::{build_query, MockError, MockExecutor, Postgres, Value};
+/// #
+/// #     pub fn find_users(db: &mut MockExecutor, name: &str) -> Result<Vec<Vec<Value>>, MockError> {
+/// #         let query = build_query!(
+/// #             #![dialect(Postgres)]
+/// #             "SELECT id, name FROM users WHERE name = " name
+/// #         );
+/// #         Ok(db.run(&query)?.rows)
+/// #     }
+/// # }
+/// let mut mock = MockExecutor::new();
+/// mock.returns_rows(vec![vec![Value::I64(1), Value::Text("kim".into())]]);
+///
+/// let users = repo::find_users(&mut mock, "kim")?;
+/// assert_eq!(users.len(), 1);
+///
+/// mock.expect_sql("SELECT id, name FROM users WHERE name = $1", &[Value::Text("kim".into())]);
+/// mock.expect_done();
+/// # Ok::<(), MockError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MockExecutor {
+    script: VecDeque<Result<MockOutcome, MockError>>,
+    executed: Vec<Executed>,
+    /// How many of `executed` the expectations have checked.
+    checked: usize,
+}
+
+impl MockExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next unanswered query with `rows`.
+    pub fn returns_rows<I>(&mut self, rows: I) -> &mut Self
+    where
+        I: IntoIterator<Item = Vec<Value>>,
+    {
+        let rows: Vec<_> = rows.into_iter().collect();
+        let affected = rows.len() as u64;
+        self.script.push_back(Ok(MockOutcome { rows, affected }));
+        self
+    }
+
+    /// Answer the next unanswered query with no rows and `affected` rows affected.
+    pub fn returns_affected(&mut self, affected: u64) -> &mut Self {
+        self.script.push_back(Ok(MockOutcome {
+            rows: Vec::new(),
+            affected,
+        }));
+        self
+    }
+
+    /// Fail the next unanswered query with `message`.
+    pub fn fails(&mut self, message: impl Into<String>) -> &mut Self {
+        self.script.push_back(Err(MockError(message.into())));
+        self
+    }
+
+    /// Record `query` and return the next scripted answer.
+    pub fn run<S, A>(&mut self, query: &Query<S, A>) -> Result<MockOutcome, MockError>
+    where
+        S: AsRef<str>,
+        A: ReadArguments,
+    {
+        self.executed.push(Executed {
+            sql: query.sql.as_ref().into(),
+            fingerprint: query.fingerprint,
+            variant: query.variant,
+            args: query.args.values().map(Value::from).collect(),
+        });
+        self.script
+            .pop_front()
+            .unwrap_or_else(|| Ok(MockOutcome::default()))
+    }
+
+    /// The queries run so far, in order.
+    pub fn executed(&self) -> &[Executed] {
+        &self.executed
+    }
+
+    /// Assert that the next query not yet checked has `sql` and `args`.
+    #[track_caller]
+    pub fn expect_sql(&mut self, sql: &str, args: &[Value]) -> &mut Self {
+        let executed = self.next_unchecked();
+        assert_eq!(executed.sql, sql, "unexpected SQL");
+        assert_eq!(executed.args, args, "unexpected args for {}", executed.sql);
+        self
+    }
+
+    /// Assert that the next query not yet checked has `fingerprint` and `args`.
+    #[track_caller]
+    pub fn expect_fingerprint(&mut self, fingerprint: Fingerprint, args: &[Value]) -> &mut Self {
+        let executed = self.next_unchecked();
+        assert_eq!(
+            executed.fingerprint, fingerprint,
+            "unexpected fingerprint for {}",
+            executed.sql
+        );
+        assert_eq!(executed.args, args, "unexpected args for {}", executed.sql);
+        self
+    }
+
+    /// Assert that the next query not yet checked is `variant` with `args`.
+    #[track_caller]
+    pub fn expect_variant(&mut self, variant: u64, args: &[Value]) -> &mut Self {
+        let executed = self.next_unchecked();
+        assert_eq!(
+            executed.variant, variant,
+            "unexpected variant for {}",
+            executed.sql
+        );
+        assert_eq!(executed.args, args, "unexpected args for {}", executed.sql);
+        self
+    }
+
+    /// Assert that every query run has been checked, and every scripted answer used.
+    #[track_caller]
+    pub fn expect_done(&self) {
+        if let Some(executed) = self.executed.get(self.checked) {
+            panic!("unchecked query: {}", executed.sql);
+        }
+        assert!(
+            self.script.is_empty(),
+            "{} scripted answers left unused",
+            self.script.len()
+        );
+    }
+
+    #[track_caller]
+    fn next_unchecked(&mut self) -> &Executed {
+        let executed = match self.executed.get(self.checked) {
+            Some(executed) => executed,
+            None => panic!("expected query #{}, but it wasn't run", self.checked + 1),
+        };
+        self.checked += 1;
+        executed
+    }
+}
@@ -0,0 +1,222 @@
+use core::fmt;
+use core::str::FromStr;
+
+/// Which way a column is sorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    /// ` ASC` or ` DESC`, to follow the column in `ORDER BY`.
+    pub fn sql(self) -> &'static str {
+        match self {
+            Direction::Asc => " ASC",
+            Direction::Desc => " DESC",
+        }
+    }
+
+    /// The comparison selecting the rows after a value in this direction.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn after(self) -> &'static str {
+        match self {
+            Direction::Asc => " > ",
+            Direction::Desc => " < ",
+        }
+    }
+}
+
+/// `asc` or `desc`, in any case.
+impl FromStr for Direction {
+    type Err = ParseOrderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("asc") {
+            Ok(Direction::Asc)
+        } else if s.eq_ignore_ascii_case("desc") {
+            Ok(Direction::Desc)
+        } else {
+            Err(ParseOrderError)
+        }
+    }
+}
+
+/// Where NULLs sort, instead of the database default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+impl Nulls {
+    /// ` NULLS FIRST` or ` NULLS LAST`.
+    pub fn sql(self) -> &'static str {
+        match self {
+            Nulls::First => " NULLS FIRST",
+            Nulls::Last => " NULLS LAST",
+        }
+    }
+}
+
+/// `first` or `last`, in any case.
+impl FromStr for Nulls {
+    type Err = ParseOrderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("first") {
+            Ok(Nulls::First)
+        } else if s.eq_ignore_ascii_case("last") {
+            Ok(Nulls::Last)
+        } else {
+            Err(ParseOrderError)
+        }
+    }
+}
+
+/// A term of `order by` in `build_query!`: a column known at compile time, with
+/// how to sort it.
+///
+/// `#[derive(OrderBy)]` implements it for enums of unit variants, each a column
+/// named after the variant in snake case unless renamed, sorted ascending. Wrap
+/// a value in [`Sort`] with [`asc`](Self::asc), [`desc`](Self::desc) or
+/// [`sort`](Self::sort) to choose the direction and where NULLs go:
+///
+/// ```
+/// # use sql_builder_test::{build_query, Direction, OrderBy};
+/// # fn main() -> Result<(), sql_builder_test::ParseOrderError> {
+/// # struct Params { sort: &'static str, direction: &'static str }
+/// # let params = Params { sort: "joined", direction: "desc" };
+/// #[derive(Clone, Copy, OrderBy)]
+/// enum UserColumn {
+///     Name,
+///     #[sql(rename = "users.created_at")]
+///     Joined,
+/// }
+///
+/// let column: UserColumn = params.sort.parse()?;
+/// let direction: Direction = params.direction.parse()?;
+/// let query = build_query!(
+///     "SELECT * FROM users"
+///     order by column.sort(direction).nulls_last(), UserColumn::Name
+/// );
+/// assert_eq!(
+///     query.sql,
+///     "SELECT * FROM users ORDER BY users.created_at DESC NULLS LAST, name ASC"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The column is rendered into the query as it is, so the trait is sealed: only
+/// the derive, [`Sort`] and [`SortKey`](crate::SortKey) implement it, all with
+/// columns written in the source. The seal has to be public for the derive to
+/// implement it in other crates, so it only keeps out implementations written
+/// by mistake, such as this one:
+///
+/// ```compile_fail
+/// struct Column(&'static str);
+///
+/// impl sql_builder_test::OrderBy for Column {
+///     fn column(&self) -> &'static str {
+///         self.0
+///     }
+/// }
+/// ```
+///
+/// Implementing the hidden `Sealed` trait by hand compiles, but isn't supported:
+/// nothing then stops a column from being built at runtime, and that it can't be
+/// is all that keeps `order by` free of injection.
+pub trait OrderBy: crate::__private::Sealed {
+    fn column(&self) -> &'static str;
+
+    fn direction(&self) -> Direction {
+        Direction::Asc
+    }
+
+    /// Where NULLs sort, or `None` for the database default.
+    fn nulls(&self) -> Option<Nulls> {
+        None
+    }
+
+    fn sort(self, direction: Direction) -> Sort<Self>
+    where
+        Self: Sized,
+    {
+        Sort {
+            column: self,
+            direction,
+            nulls: None,
+        }
+    }
+
+    fn asc(self) -> Sort<Self>
+    where
+        Self: Sized,
+    {
+        self.sort(Direction::Asc)
+    }
+
+    fn desc(self) -> Sort<Self>
+    where
+        Self: Sized,
+    {
+        self.sort(Direction::Desc)
+    }
+}
+
+/// A column with the direction and NULLs order to sort it in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Sort<T> {
+    pub column: T,
+    pub direction: Direction,
+    pub nulls: Option<Nulls>,
+}
+
+impl<T> Sort<T> {
+    #[must_use]
+    pub fn with_nulls(mut self, nulls: Option<Nulls>) -> Self {
+        self.nulls = nulls;
+        self
+    }
+
+    #[must_use]
+    pub fn nulls_first(self) -> Self {
+        self.with_nulls(Some(Nulls::First))
+    }
+
+    #[must_use]
+    pub fn nulls_last(self) -> Self {
+        self.with_nulls(Some(Nulls::Last))
+    }
+}
+
+impl<T: OrderBy> crate::__private::Sealed for Sort<T> {}
+
+impl<T: OrderBy> OrderBy for Sort<T> {
+    fn column(&self) -> &'static str {
+        self.column.column()
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn nulls(&self) -> Option<Nulls> {
+        self.nulls
+    }
+}
+
+/// A sort column, direction or NULLs order that isn't one of those known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOrderError;
+
+impl fmt::Display for ParseOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown sort order")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseOrderError {}
@@ -0,0 +1,133 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+/// The sink used when none is given: a `String` with `alloc`, otherwise a
+/// [`FixedBuf`] of [`DEFAULT_FIXED_CAPACITY`] bytes.
+#[cfg(feature = "alloc")]
+pub type DefaultSink = String;
+
+#[cfg(not(feature = "alloc"))]
+pub type DefaultSink = FixedBuf<DEFAULT_FIXED_CAPACITY>;
+
+pub const DEFAULT_FIXED_CAPACITY: usize = 1024;
+
+/// An output buffer that rendered SQL is written into.
+///
+/// Implemented for the common owned buffers, for `&mut` references to any sink
+/// (so a pooled buffer can be reused across queries) and for [`FixedBuf`].
+pub trait Sink {
+    fn push_str(&mut self, s: &str) -> fmt::Result;
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for String {
+    #[inline]
+    fn push_str(&mut self, s: &str) -> fmt::Result {
+        String::push_str(self, s);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for Vec<u8> {
+    #[inline]
+    fn push_str(&mut self, s: &str) -> fmt::Result {
+        self.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Sink for bytes::BytesMut {
+    #[inline]
+    fn push_str(&mut self, s: &str) -> fmt::Result {
+        self.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    #[inline]
+    fn push_str(&mut self, s: &str) -> fmt::Result {
+        (**self).push_str(s)
+    }
+}
+
+/// A fixed-capacity sink that never allocates.
+///
+/// Writing past the capacity fails, which makes the builder report an overflow.
+pub struct FixedBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FixedBuf<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole `&str`s are ever copied in, so this is always valid UTF-8.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for FixedBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Sink for FixedBuf<N> {
+    fn push_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > N {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedBuf<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> AsRef<str> for FixedBuf<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for FixedBuf<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// Adapts a [`Sink`] to `fmt::Write`, for formatting placeholders.
+pub(crate) struct FmtSink<'s, S: ?Sized> {
+    pub sink: &'s mut S,
+    /// Bytes written so far.
+    pub len: usize,
+}
+
+impl<S: Sink + ?Sized> fmt::Write for FmtSink<'_, S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.sink.push_str(s)?;
+        self.len += s.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "splice-queries")]
+use crate::Query;
+use crate::{Arguments, BuildError, Builder, DefaultArgs, Dialect, Fingerprint, Generic, Sink};
+
+/// SQL with its own binds that `..expr` in `build_query!` can splice in, into a
+/// builder collecting them into `A`.
+pub trait Splice<A: Arguments> {
+    /// Render into `builder`, numbering placeholders after the ones already rendered.
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>);
+}
+
+/// Only the SQL the query rendered is spliced in, not what was in its sink before.
+#[cfg(feature = "splice-queries")]
+impl<T: AsRef<str>, A: Arguments> Splice<A> for Query<T, A> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        let sql = self.sql.as_ref();
+        let sql = &sql[sql.len() - self.sql_len..];
+        let mut start = 0;
+        for placeholder in &self.placeholders {
+            builder.push_sql_from(&sql[start..placeholder.start], None);
+            builder.push_sql_arg_from(None);
+            start = placeholder.end;
+        }
+        builder.push_sql_from(&sql[start..], None);
+        builder.args.append(self.args);
+        builder.args_size += self.args_size;
+        builder.fold_fingerprint(self.fingerprint.0);
+        builder.fold_variant(self.variant);
+    }
+}
+
+/// A piece of SQL built by `sql_fragment!`, with the binds of its placeholders
+/// but not yet their numbers, to be combined with others and spliced into
+/// `build_query!` with `..fragment`.
+///
+/// Like a `Query`, its SQL only ever comes from string literals. Errors are
+/// reported when the query it is spliced into is built, and so is an empty IN
+/// list, which only fails if that query's dialect doesn't allow it.
+#[derive(Clone, Debug)]
+pub struct SqlFragment<A = DefaultArgs> {
+    sql: String,
+    /// Byte offsets in `sql` where placeholders go.
+    slots: Vec<usize>,
+    args: A,
+    args_size: usize,
+    fingerprint: Fingerprint,
+    /// The branches taken building it, folded into the variant of the query it
+    /// is spliced into.
+    variant: u64,
+    error: Option<BuildError>,
+    empty_in_list: bool,
+    /// Whether `sql` is parenthesized as a whole, as `and` and `or` leave it.
+    grouped: bool,
+}
+
+impl<A: Arguments> Default for SqlFragment<A> {
+    fn default() -> Self {
+        SqlFragment {
+            sql: String::new(),
+            slots: Vec::new(),
+            args: A::default(),
+            args_size: 0,
+            fingerprint: Fingerprint::EMPTY,
+            variant: Fingerprint::EMPTY.0,
+            error: None,
+            empty_in_list: false,
+            grouped: false,
+        }
+    }
+}
+
+impl<A: Arguments> SqlFragment<A> {
+    /// A fragment rendering nothing, which `and`, `or` and `join` skip.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sql.is_empty() && self.slots.is_empty()
+    }
+
+    pub fn args_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// `((self) AND (other))`, or whichever is non-empty. Operands that are
+    /// already parenthesized aren't wrapped again.
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        self.combine(" AND ", other)
+    }
+
+    /// `((self) OR (other))`, or whichever is non-empty. Operands that are
+    /// already parenthesized aren't wrapped again.
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        self.combine(" OR ", other)
+    }
+
+    /// The non-empty `fragments` separated by `separator`, without parentheses.
+    pub fn join<I>(separator: &'static str, fragments: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let mut joined = Self::default();
+        for fragment in fragments {
+            if fragment.is_empty() {
+                continue;
+            }
+            if !joined.is_empty() {
+                joined.push_sql(separator);
+            }
+            joined.append(fragment);
+        }
+        joined
+    }
+
+    fn combine(self, operator: &'static str, other: Self) -> Self {
+        if other.is_empty() {
+            return self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+        let mut combined = Self::default();
+        combined.push_sql("(");
+        combined.append_grouped(self);
+        combined.push_sql(operator);
+        combined.append_grouped(other);
+        combined.push_sql(")");
+        combined.grouped = true;
+        combined
+    }
+
+    fn append_grouped(&mut self, other: Self) {
+        if other.grouped {
+            self.append(other);
+        } else {
+            self.push_sql("(");
+            self.append(other);
+            self.push_sql(")");
+        }
+    }
+
+    fn push_sql(&mut self, sql: &'static str) {
+        let hash = Fingerprint::hash(sql.as_bytes());
+        self.sql.push_str(sql);
+        self.fingerprint = self.fingerprint.fold(hash);
+        self.variant = Fingerprint(self.variant).fold(hash).0;
+    }
+
+    fn append(&mut self, other: Self) {
+        let offset = self.sql.len();
+        self.sql.push_str(&other.sql);
+        self.slots
+            .extend(other.slots.iter().map(|slot| offset + slot));
+        self.args.append(other.args);
+        self.args_size += other.args_size;
+        self.fingerprint = self.fingerprint.fold(other.fingerprint.0);
+        self.variant = Fingerprint(self.variant).fold(other.variant).0;
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+        self.empty_in_list |= other.empty_in_list;
+    }
+}
+
+/// Clones the arguments, which splicing a fragment by value avoids.
+impl<A: Arguments + Clone> Splice<A> for &SqlFragment<A> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        self.clone().splice_into(builder);
+    }
+}
+
+impl<A: Arguments> Splice<A> for SqlFragment<A> {
+    fn splice_into<S: Sink, D: Dialect>(self, builder: &mut Builder<S, D, A>) {
+        let mut start = 0;
+        for &slot in &self.slots {
+            builder.push_sql_from(&self.sql[start..slot], None);
+            builder.push_sql_arg_from(None);
+            start = slot;
+        }
+        builder.push_sql_from(&self.sql[start..], None);
+        builder.args.append(self.args);
+        builder.args_size += self.args_size;
+        builder.fold_fingerprint(self.fingerprint.0);
+        builder.fold_variant(self.variant);
+        builder.empty_in_list |= self.empty_in_list;
+        if let Some(error) = self.error {
+            builder.fail(error);
+        }
+    }
+}
+
+impl<A: Arguments> Builder<String, Generic, A> {
+    /// A builder for `sql_fragment!`, keeping track of where it renders placeholders.
+    pub fn fragment() -> Self {
+        let mut builder = Self::with_sink(String::new());
+        builder.placeholders = Some(Vec::new());
+        builder
+    }
+
+    /// Finish as a [`SqlFragment`] instead of a query, for `sql_fragment!`.
+    ///
+    /// Panics if the builder wasn't made with [`fragment`](Self::fragment).
+    pub fn into_fragment(self) -> SqlFragment<A> {
+        let placeholders = self
+            .placeholders
+            .expect("fragments are built with Builder::fragment");
+        let mut sql = String::with_capacity(self.len);
+        let mut slots = Vec::with_capacity(placeholders.len());
+        let mut start = 0;
+        for placeholder in &placeholders {
+            sql.push_str(&self.sql[start..placeholder.start]);
+            slots.push(sql.len());
+            start = placeholder.end;
+        }
+        sql.push_str(&self.sql[start..]);
+        SqlFragment {
+            sql,
+            slots,
+            args: self.args,
+            args_size: self.args_size,
+            fingerprint: self.fingerprint,
+            variant: self.variant,
+            error: self.error,
+            empty_in_list: self.empty_in_list,
+            grouped: false,
+        }
+    }
+}
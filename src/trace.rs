@@ -0,0 +1,116 @@
+#[cfg(feature = "std")]
+use core::convert::TryFrom;
+
+use crate::{BuildError, Fingerprint, Location, Query};
+
+const TARGET: &str = "sql_builder";
+
+pub(crate) struct Built<'a> {
+    pub call_site: Option<&'static Location>,
+    pub variant: u64,
+    pub fingerprint: Fingerprint,
+    pub args_count: usize,
+    pub sql_len: usize,
+    pub error: Option<&'a BuildError>,
+}
+
+/// Report a query built, or failing to build, as a `tracing` event.
+pub(crate) fn built(built: Built<'_>) {
+    let call_site = built.call_site.map(tracing::field::display);
+    match built.error {
+        None => tracing::debug!(
+            target: TARGET,
+            call_site,
+            variant = format_args!("{:016x}", built.variant),
+            fingerprint = %built.fingerprint,
+            args_count = built.args_count,
+            sql_len = built.sql_len,
+            "built query"
+        ),
+        Some(error) => tracing::warn!(
+            target: TARGET,
+            call_site,
+            variant = format_args!("{:016x}", built.variant),
+            fingerprint = %built.fingerprint,
+            args_count = built.args_count,
+            sql_len = built.sql_len,
+            %error,
+            "failed to build query"
+        ),
+    }
+}
+
+impl<S, A> Query<S, A> {
+    /// A span for executing this query in, with empty `rows`, `duration_us` and
+    /// `error` fields for the executor to record.
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            target: TARGET,
+            "query",
+            call_site = self.call_site.map(tracing::field::display),
+            variant = format_args!("{:016x}", self.variant),
+            fingerprint = %self.fingerprint,
+            args_count = self.args_count,
+            rows = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        )
+    }
+
+    /// Start timing an execution of this query, in its [`span`](Self::span).
+    #[cfg(feature = "std")]
+    pub fn execution(&self) -> Execution {
+        Execution {
+            span: self.span(),
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+/// An execution of a query in progress, for driver adapters:
+///
+/// ```
+/// # use sql_builder_test::build_query;
+/// # let execute = |_sql: &str| Ok::<usize, std::fmt::Error>(1);
+/// let query = build_query!("UPDATE users SET active = " false);
+/// let execution = query.execution();
+/// match execution.in_scope(|| execute(&query.sql)) {
+///     Ok(rows) => execution.finish(rows as u64),
+///     Err(error) => execution.fail(&error),
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct Execution {
+    span: tracing::Span,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Execution {
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Run `f` inside the span.
+    pub fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.span.in_scope(f)
+    }
+
+    /// Record the rows returned or affected and the time taken.
+    pub fn finish(self, rows: u64) {
+        self.span.record("rows", rows);
+        self.record_duration();
+    }
+
+    /// Record that the execution failed, and the time taken.
+    pub fn fail(self, error: &dyn core::fmt::Display) {
+        self.span.record("error", tracing::field::display(error));
+        self.record_duration();
+    }
+
+    fn record_duration(&self) {
+        let micros = self.start.elapsed().as_micros();
+        self.span
+            .record("duration_us", u64::try_from(micros).unwrap_or(u64::MAX));
+    }
+}
@@ -0,0 +1,223 @@
+//! Postgres extended query protocol messages, for sending a `Query` without a driver.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::{Query, ReadArguments, ValueRef};
+
+mod oid {
+    /// Leaves the type to the server, as for NULL.
+    pub const UNSPECIFIED: u32 = 0;
+    pub const BOOL: u32 = 16;
+    pub const BYTEA: u32 = 17;
+    pub const INT8: u32 = 20;
+    pub const INT2: u32 = 21;
+    pub const INT4: u32 = 23;
+    pub const TEXT: u32 = 25;
+    pub const FLOAT4: u32 = 700;
+    pub const FLOAT8: u32 = 701;
+    pub const NUMERIC: u32 = 1700;
+}
+
+const TEXT_FORMAT: u16 = 0;
+const BINARY_FORMAT: u16 = 1;
+
+/// Why messages could not be written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WireError {
+    /// The SQL or a statement or portal name contains a NUL byte, which would end
+    /// it early on the wire.
+    Nul,
+    /// More values than the 65535 a `Bind` message can carry.
+    TooManyParameters { count: usize },
+    /// A message longer than the protocol's `i32` length allows.
+    MessageTooLong,
+    /// The query wasn't built with placeholders numbered `$1`, `$2`, ..., as
+    /// with `#![dialect(Postgres)]`, so postgres wouldn't see its parameters.
+    Placeholders,
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Nul => write!(f, "NUL byte in a string sent to postgres"),
+            WireError::TooManyParameters { count } => write!(
+                f,
+                "query has {} parameters, but postgres allows at most 65535",
+                count
+            ),
+            WireError::MessageTooLong => write!(f, "postgres message longer than i32::MAX bytes"),
+            WireError::Placeholders => {
+                write!(
+                    f,
+                    "query placeholders aren't numbered $1, $2, ... as postgres expects"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WireError {}
+
+/// The type and format a value is sent with. `u64` doesn't fit any integer
+/// type, so it goes as `numeric` text. Custom values go as their fallback.
+fn param_type(value: ValueRef<'_>) -> (u32, u16) {
+    let oid = match value {
+        ValueRef::Null => oid::UNSPECIFIED,
+        ValueRef::Bool(_) => oid::BOOL,
+        ValueRef::I16(_) => oid::INT2,
+        ValueRef::I32(_) => oid::INT4,
+        ValueRef::I64(_) => oid::INT8,
+        ValueRef::U64(_) => return (oid::NUMERIC, TEXT_FORMAT),
+        ValueRef::F32(_) => oid::FLOAT4,
+        ValueRef::F64(_) => oid::FLOAT8,
+        ValueRef::Text(_) => oid::TEXT,
+        ValueRef::Bytes(_) => oid::BYTEA,
+        ValueRef::Custom(value) => return param_type(value.fallback()),
+    };
+    (oid, BINARY_FORMAT)
+}
+
+fn write_value(value: ValueRef<'_>, out: &mut Vec<u8>) -> Result<(), WireError> {
+    match value {
+        ValueRef::Null => {
+            out.extend_from_slice(&(-1_i32).to_be_bytes());
+            Ok(())
+        }
+        ValueRef::Bool(value) => write_bytes(&[value as u8], out),
+        ValueRef::I16(value) => write_bytes(&value.to_be_bytes(), out),
+        ValueRef::I32(value) => write_bytes(&value.to_be_bytes(), out),
+        ValueRef::I64(value) => write_bytes(&value.to_be_bytes(), out),
+        ValueRef::U64(value) => write_bytes(value.to_string().as_bytes(), out),
+        ValueRef::F32(value) => write_bytes(&value.to_bits().to_be_bytes(), out),
+        ValueRef::F64(value) => write_bytes(&value.to_bits().to_be_bytes(), out),
+        ValueRef::Text(value) => write_bytes(value.as_bytes(), out),
+        ValueRef::Bytes(value) => write_bytes(value, out),
+        ValueRef::Custom(value) => write_value(value.fallback(), out),
+    }
+}
+
+fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) -> Result<(), WireError> {
+    let len = i32::try_from(bytes.len()).map_err(|_| WireError::MessageTooLong)?;
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_cstr(s: &str, out: &mut Vec<u8>) -> Result<(), WireError> {
+    if s.as_bytes().contains(&0) {
+        return Err(WireError::Nul);
+    }
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+fn write_count(count: usize, out: &mut Vec<u8>) -> Result<(), WireError> {
+    let count = u16::try_from(count).map_err(|_| WireError::TooManyParameters { count })?;
+    out.extend_from_slice(&count.to_be_bytes());
+    Ok(())
+}
+
+/// Write a message with its tag and length, leaving `out` as it was on errors.
+fn write_message<F>(tag: u8, out: &mut Vec<u8>, body: F) -> Result<(), WireError>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<(), WireError>,
+{
+    let start = out.len();
+    out.push(tag);
+    out.extend_from_slice(&[0; 4]);
+    let result = body(out).and_then(|()| {
+        let len = i32::try_from(out.len() - start - 1).map_err(|_| WireError::MessageTooLong)?;
+        out[start + 1..start + 5].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    });
+    if result.is_err() {
+        out.truncate(start);
+    }
+    result
+}
+
+/// Write an `Execute` message, running `portal` for at most `max_rows` rows, or
+/// all of them if 0.
+pub fn write_execute(portal: &str, max_rows: u32, out: &mut Vec<u8>) -> Result<(), WireError> {
+    let max_rows = i32::try_from(max_rows).unwrap_or(i32::MAX);
+    write_message(b'E', out, |out| {
+        write_cstr(portal, out)?;
+        out.extend_from_slice(&max_rows.to_be_bytes());
+        Ok(())
+    })
+}
+
+/// Write a `Sync` message, ending the extended query.
+pub fn write_sync(out: &mut Vec<u8>) {
+    out.extend_from_slice(&[b'S', 0, 0, 0, 4]);
+}
+
+/// The query must have been built with `#![dialect(Postgres)]`, or another dialect
+/// numbering placeholders `$1`, `$2`, ...; [`write_parse`](Query::write_parse) fails
+/// with [`WireError::Placeholders`] otherwise.
+impl<S: AsRef<str>, A: ReadArguments> Query<S, A> {
+    /// Write a `Parse` message preparing the SQL as `statement`, or the unnamed
+    /// statement if empty, with parameter types following the bound values.
+    /// NULLs leave their type to the server.
+    pub fn write_parse(&self, statement: &str, out: &mut Vec<u8>) -> Result<(), WireError> {
+        if !self.postgres_placeholders {
+            return Err(WireError::Placeholders);
+        }
+        write_message(b'P', out, |out| {
+            write_cstr(statement, out)?;
+            write_cstr(self.sql.as_ref(), out)?;
+            write_count(self.args.len(), out)?;
+            for value in self.args.values() {
+                let (oid, _) = param_type(value);
+                out.extend_from_slice(&oid.to_be_bytes());
+            }
+            Ok(())
+        })
+    }
+
+    /// Write a `Bind` message binding the values to `statement` as `portal`,
+    /// in binary format, and asking for results in text format.
+    pub fn write_bind(
+        &self,
+        portal: &str,
+        statement: &str,
+        out: &mut Vec<u8>,
+    ) -> Result<(), WireError> {
+        write_message(b'B', out, |out| {
+            write_cstr(portal, out)?;
+            write_cstr(statement, out)?;
+            write_count(self.args.len(), out)?;
+            for value in self.args.values() {
+                let (_, format) = param_type(value);
+                out.extend_from_slice(&format.to_be_bytes());
+            }
+            write_count(self.args.len(), out)?;
+            for value in self.args.values() {
+                write_value(value, out)?;
+            }
+            // No result format codes: all text.
+            out.extend_from_slice(&0_u16.to_be_bytes());
+            Ok(())
+        })
+    }
+
+    /// Write `Parse`, `Bind` and `Execute` messages running the query once with
+    /// the unnamed statement and portal. Follow them with [`write_sync`] to have
+    /// the server respond.
+    pub fn write_extended(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        let start = out.len();
+        let result = self
+            .write_parse("", out)
+            .and_then(|()| self.write_bind("", "", out))
+            .and_then(|()| write_execute("", 0, out));
+        if result.is_err() {
+            out.truncate(start);
+        }
+        result
+    }
+}
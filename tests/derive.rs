@@ -0,0 +1,362 @@
+use sql_builder_test::{
+    BuildError, Direction, Generic, Nulls, OrderBy, ParseOrderError, Postgres, SqlFilter,
+    SqlInsert, SqlUpdate, Sqlite,
+};
+
+#[derive(SqlInsert)]
+#[sql(table = "users", returning = "id, created_at")]
+struct NewUser<'a> {
+    name: &'a str,
+    #[sql(rename = "email_address")]
+    email: &'a str,
+    #[sql(default)]
+    role: Option<&'a str>,
+    #[sql(skip)]
+    #[allow(dead_code)]
+    password: &'a str,
+    r#type: i32,
+}
+
+fn new_user(role: Option<&str>) -> NewUser<'_> {
+    NewUser {
+        name: "x",
+        email: "x@example.com",
+        role,
+        password: "secret",
+        r#type: 1,
+    }
+}
+
+#[test]
+fn insert_lists_columns_and_binds_fields() {
+    let query = new_user(Some("admin")).insert_query::<Postgres>();
+    assert_eq!(
+        query.sql,
+        "INSERT INTO users (name, email_address, role, type) VALUES ($1, $2, $3, $4) RETURNING id, created_at"
+    );
+    assert_eq!(query.args_count, 4);
+}
+
+#[test]
+fn insert_leaves_out_default_columns_when_none() {
+    let query = new_user(None).insert_query::<Sqlite>();
+    assert_eq!(
+        query.sql,
+        "INSERT INTO users (name, email_address, type) VALUES (?1, ?2, ?3) RETURNING id, created_at"
+    );
+    let admin = new_user(Some("admin")).insert_query::<Sqlite>();
+    assert_ne!(query.fingerprint, admin.fingerprint);
+    assert_ne!(query.variant, admin.variant);
+}
+
+#[derive(SqlInsert)]
+#[sql(table = "events")]
+struct Event {
+    #[sql(default)]
+    at: Option<u64>,
+}
+
+#[test]
+fn insert_uses_default_values_when_nothing_is_set() {
+    assert_eq!(
+        Event { at: None }.insert_query::<Generic>().sql,
+        "INSERT INTO events DEFAULT VALUES"
+    );
+    assert_eq!(
+        Event { at: Some(1) }.insert_query::<Generic>().sql,
+        "INSERT INTO events (at) VALUES ($0)"
+    );
+}
+
+#[cfg(feature = "splice-queries")]
+#[test]
+fn insert_splices_into_build_query() {
+    use sql_builder_test::build_query;
+
+    let query = build_query!(
+        #![dialect(Postgres)]
+        "WITH t AS (SELECT 1) "
+        ..Event { at: Some(1) }.insert_query::<Generic>()
+        " ON CONFLICT DO NOTHING"
+    );
+    assert_eq!(
+        query.sql,
+        "WITH t AS (SELECT 1) INSERT INTO events (at) VALUES ($1) ON CONFLICT DO NOTHING"
+    );
+}
+
+#[derive(SqlUpdate)]
+#[sql(table = "users", returning = "updated_at")]
+struct PatchUser<'a> {
+    #[sql(key)]
+    id: i64,
+    #[sql(key, rename = "org_id")]
+    org: i64,
+    name: Option<&'a str>,
+    nickname: Option<Option<&'a str>>,
+    #[sql(skip)]
+    #[allow(dead_code)]
+    etag: Option<&'a str>,
+}
+
+fn patch<'a>(name: Option<&'a str>, nickname: Option<Option<&'a str>>) -> PatchUser<'a> {
+    PatchUser {
+        id: 7,
+        org: 1,
+        name,
+        nickname,
+        etag: Some("x"),
+    }
+}
+
+#[test]
+fn update_sets_present_fields() {
+    let query = patch(Some("x"), Some(None))
+        .update_query::<Postgres>()
+        .unwrap();
+    assert_eq!(
+        query.sql,
+        "UPDATE users SET name = $1, nickname = $2 WHERE id = $3 AND org_id = $4 RETURNING updated_at"
+    );
+    let query = patch(None, Some(Some("y")))
+        .update_query::<Sqlite>()
+        .unwrap();
+    assert_eq!(
+        query.sql,
+        "UPDATE users SET nickname = ?1 WHERE id = ?2 AND org_id = ?3 RETURNING updated_at"
+    );
+    assert_ne!(
+        query.fingerprint,
+        patch(Some("x"), None)
+            .update_query::<Sqlite>()
+            .unwrap()
+            .fingerprint
+    );
+}
+
+#[test]
+fn update_fails_when_nothing_is_set() {
+    assert_eq!(
+        patch(None, None).update_query::<Postgres>().err(),
+        Some(BuildError::EmptySet)
+    );
+}
+
+#[derive(SqlUpdate)]
+#[sql(table = "counters")]
+struct SetCounter {
+    #[sql(key)]
+    name: &'static str,
+    value: i64,
+}
+
+#[test]
+fn update_always_sets_required_fields() {
+    let counter = SetCounter {
+        name: "hits",
+        value: 3,
+    };
+    assert_eq!(
+        counter.update_query::<Generic>().unwrap().sql,
+        "UPDATE counters SET value = $0 WHERE name = $1"
+    );
+}
+
+#[derive(SqlFilter)]
+struct UserFilter<'a> {
+    org_id: i64,
+    role: Option<&'a str>,
+    #[sql(rename = "id")]
+    ids: Vec<i64>,
+    #[sql(gte = "created_at")]
+    since: Option<i64>,
+    #[sql(ilike = "name")]
+    search: Option<&'a str>,
+    #[sql(skip)]
+    #[allow(dead_code)]
+    page: u32,
+}
+
+/// Every combination of present and absent fields.
+fn user_filters() -> impl Iterator<Item = (UserFilter<'static>, Vec<&'static str>)> {
+    (0..16).map(|mask: u32| {
+        let present = |bit: u32| mask & (1 << bit) != 0;
+        let mut expected = vec!["org_id = ?"];
+        let filter = UserFilter {
+            org_id: 1,
+            role: if present(0) { Some("admin") } else { None },
+            ids: if present(1) { vec![1, 2] } else { vec![] },
+            since: if present(2) { Some(1_000) } else { None },
+            search: if present(3) { Some("%x%") } else { None },
+            page: 3,
+        };
+        for (bit, predicate) in [
+            "role = ?",
+            "id IN (?, ?)",
+            "created_at >= ?",
+            "name ILIKE ?",
+        ]
+        .iter()
+        .enumerate()
+        {
+            if present(bit as u32) {
+                expected.push(predicate);
+            }
+        }
+        (filter, expected)
+    })
+}
+
+#[test]
+fn filter_joins_present_predicates() {
+    for (filter, expected) in user_filters() {
+        let mut builder = sql_builder_test::Builder::new();
+        filter.push_filter(&mut builder);
+        let query = builder.build();
+        let mut numbered = String::new();
+        for (index, part) in expected.join(" AND ").split('?').enumerate() {
+            if index > 0 {
+                numbered.push_str(&format!("${}", index - 1));
+            }
+            numbered.push_str(part);
+        }
+        assert_eq!(query.sql.as_ref(), numbered);
+        assert_eq!(query.args_count, expected.join("").matches('?').count());
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn filter_splices_into_where_groups() {
+    use sql_builder_test::build_query;
+
+    let queries: Vec<String> = user_filters()
+        .map(|(filter, _)| {
+            build_query!(
+                #![dialect(Postgres)]
+                "SELECT * FROM users"
+                where {
+                    if filter.page > 10 { "archived" }
+                    ..filter.filter_fragment()
+                }
+            )
+            .sql
+        })
+        .collect();
+    assert_eq!(queries[0], "SELECT * FROM users WHERE org_id = $1");
+    assert_eq!(
+        queries[15],
+        "SELECT * FROM users WHERE org_id = $1 AND role = $2 AND id IN ($3, $4) AND created_at >= $5 AND name ILIKE $6"
+    );
+    assert_eq!(
+        queries[0..16]
+            .iter()
+            .collect::<std::collections::BTreeSet<_>>()
+            .len(),
+        16
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn filter_renders_ilike_for_the_dialect() {
+    use sql_builder_test::build_query;
+
+    let filter = UserFilter {
+        org_id: 1,
+        role: None,
+        ids: vec![],
+        since: None,
+        search: Some("%x%"),
+        page: 3,
+    };
+    let sqlite = build_query!(
+        #![dialect(Sqlite)]
+        "SELECT * FROM users"
+        where { ..filter.predicates() }
+    );
+    assert_eq!(
+        sqlite.sql,
+        "SELECT * FROM users WHERE org_id = ?1 AND name LIKE ?2"
+    );
+    let postgres = build_query!(
+        #![dialect(Postgres)]
+        "SELECT * FROM users"
+        where { ..filter.predicates() }
+    );
+    assert_eq!(
+        postgres.sql,
+        "SELECT * FROM users WHERE org_id = $1 AND name ILIKE $2"
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, OrderBy)]
+enum UserColumn {
+    Name,
+    #[sql(rename = "users.created_at")]
+    CreatedAt,
+    Id,
+}
+
+fn ordered(column: &str, direction: &str, nulls: Option<Nulls>) -> Result<String, ParseOrderError> {
+    use sql_builder_test::build_query;
+
+    let column: UserColumn = column.parse()?;
+    let direction: Direction = direction.parse()?;
+    let query = build_query!(
+        #![dialect(Sqlite)]
+        "SELECT * FROM users WHERE org_id = " 1
+        order by column.sort(direction).with_nulls(nulls), UserColumn::Id
+        " LIMIT " 20
+    );
+    let sql: &str = query.sql.as_ref();
+    Ok(sql.to_owned())
+}
+
+#[test]
+fn order_by_renders_the_chosen_column() {
+    assert_eq!(
+        ordered("created_at", "DESC", Some(Nulls::Last)).unwrap(),
+        "SELECT * FROM users WHERE org_id = ?1 ORDER BY users.created_at DESC NULLS LAST, id ASC LIMIT ?2"
+    );
+    assert_eq!(
+        ordered("name", "asc", None).unwrap(),
+        "SELECT * FROM users WHERE org_id = ?1 ORDER BY name ASC, id ASC LIMIT ?2"
+    );
+}
+
+#[test]
+fn order_by_rejects_unknown_input() {
+    assert_eq!(
+        ordered("users.created_at", "asc", None),
+        Err(ParseOrderError)
+    );
+    assert_eq!(
+        ordered("name; DROP TABLE users", "asc", None),
+        Err(ParseOrderError)
+    );
+    assert_eq!(ordered("name", "sideways", None), Err(ParseOrderError));
+    assert_eq!("first".parse(), Ok(Nulls::First));
+}
+
+#[test]
+fn order_by_fingerprints_the_ordering() {
+    use sql_builder_test::build_query;
+
+    let fingerprint = |sort: &dyn Fn() -> sql_builder_test::Sort<UserColumn>| {
+        build_query!("SELECT * FROM users" order by sort()).fingerprint
+    };
+    assert_eq!(
+        fingerprint(&|| UserColumn::Name.asc()),
+        fingerprint(&|| UserColumn::Name.asc())
+    );
+    assert_ne!(
+        fingerprint(&|| UserColumn::Name.asc()),
+        fingerprint(&|| UserColumn::Name.desc())
+    );
+    assert_ne!(
+        fingerprint(&|| UserColumn::Name.asc()),
+        fingerprint(&|| UserColumn::Id.asc())
+    );
+    assert_eq!(UserColumn::CreatedAt.column(), "users.created_at");
+}
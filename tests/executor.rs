@@ -0,0 +1,138 @@
+#![cfg(feature = "executor")]
+
+use futures_executor::block_on;
+use sql_builder_test::{build_query, ExecuteError, Executor, MockExecutor, Sqlite, Value};
+
+/// Repository code, written once for any backend.
+async fn user_names(db: &mut impl Executor, org: i64) -> Result<Vec<String>, ExecuteError> {
+    let query = build_query!(
+        #![dialect(Sqlite)]
+        "SELECT name FROM users WHERE org = " org " ORDER BY name"
+    );
+    let rows = db.fetch_all(&query).await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|mut row| match row.pop() {
+            Some(Value::Text(name)) => Some(name),
+            _ => None,
+        })
+        .collect())
+}
+
+async fn rename_user(db: &mut impl Executor, id: i64, name: &str) -> Result<u64, ExecuteError> {
+    db.execute(&build_query!(
+        #![dialect(Sqlite)]
+        "UPDATE users SET name = " name " WHERE id = " id
+    ))
+    .await
+}
+
+#[test]
+fn runs_against_the_mock() {
+    let mut mock = MockExecutor::new();
+    mock.returns_rows(vec![
+        vec![Value::Text("kim".into())],
+        vec![Value::Text("lee".into())],
+    ])
+    .returns_affected(1);
+
+    assert_eq!(block_on(user_names(&mut mock, 7)).unwrap(), ["kim", "lee"]);
+    assert_eq!(block_on(rename_user(&mut mock, 3, "sam")).unwrap(), 1);
+
+    mock.expect_sql(
+        "SELECT name FROM users WHERE org = ?1 ORDER BY name",
+        &[Value::I64(7)],
+    )
+    .expect_sql(
+        "UPDATE users SET name = ?1 WHERE id = ?2",
+        &[Value::Text("sam".into()), Value::I64(3)],
+    )
+    .expect_done();
+}
+
+#[test]
+fn fetch_one_requires_a_row() {
+    let mut mock = MockExecutor::new();
+    let query = build_query!("SELECT 1");
+    assert!(matches!(
+        block_on(mock.fetch_one(&query)),
+        Err(ExecuteError::RowNotFound)
+    ));
+    assert_eq!(block_on(mock.fetch_optional(&query)).unwrap(), None);
+}
+
+#[test]
+fn mock_errors_are_backend_errors() {
+    let mut mock = MockExecutor::new();
+    mock.fails("connection reset");
+    let error = block_on(user_names(&mut mock, 7)).unwrap_err();
+    assert!(matches!(error, ExecuteError::Backend(_)));
+    assert_eq!(error.to_string(), "connection reset");
+}
+
+#[cfg(feature = "rusqlite")]
+mod sqlite {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use rusqlite::Connection;
+
+    fn users() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, org INTEGER, name TEXT, avatar BLOB);
+             INSERT INTO users VALUES (1, 7, 'lee', NULL), (2, 7, 'kim', x'00ff'), (3, 8, 'sam', NULL);",
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn runs_against_sqlite() {
+        let mut db = users();
+        assert_eq!(block_on(user_names(&mut db, 7)).unwrap(), ["kim", "lee"]);
+        assert_eq!(block_on(rename_user(&mut db, 3, "ann")).unwrap(), 1);
+        assert_eq!(block_on(user_names(&mut db, 8)).unwrap(), ["ann"]);
+    }
+
+    #[test]
+    fn decodes_columns() {
+        let mut db = users();
+        let id = 2;
+        let query = build_query!(#![dialect(Sqlite)] "SELECT * FROM users WHERE id = " id);
+        assert_eq!(
+            block_on(db.fetch_one(&query)).unwrap(),
+            [
+                Value::I64(2),
+                Value::I64(7),
+                Value::Text("kim".into()),
+                Value::Bytes(vec![0, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn streams_rows() {
+        let mut db = users();
+        let query = build_query!(#![dialect(Sqlite)] "SELECT id FROM users ORDER BY id DESC");
+        let ids: Vec<_> = block_on(db.stream(&query).try_collect()).unwrap();
+        assert_eq!(ids, [[Value::I64(3)], [Value::I64(2)], [Value::I64(1)]]);
+    }
+
+    #[test]
+    fn rejects_values_sqlite_cant_hold() {
+        let mut db = users();
+        let id = u64::MAX;
+        let query = build_query!(#![dialect(Sqlite)] "SELECT * FROM users WHERE id = " id);
+        assert!(matches!(
+            block_on(db.fetch_all(&query)),
+            Err(ExecuteError::UnsupportedValue(Value::U64(u64::MAX)))
+        ));
+    }
+}
+
+#[cfg(feature = "tokio-postgres")]
+#[test]
+fn tokio_postgres_clients_are_executors() {
+    fn executor<E: Executor>() {}
+    executor::<tokio_postgres::Client>();
+}
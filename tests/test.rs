@@ -15,3 +15,1362 @@ fn its_actually_working_with_proc_macro_hack() {
     assert_eq!(query.args_count, 1);
     assert_eq!(query.args_size, 4);
 }
+
+#[cfg(feature = "alloc")]
+#[test]
+fn renders_into_caller_provided_sink() {
+    let mut buf = String::from("/* pooled */ ");
+    let id = 7_i64;
+    let query = build_query!(
+        #![sink(&mut buf)]
+        "SELECT * FROM lol WHERE lol.id = " id
+    );
+    assert_eq!(query.args_count, 1);
+    assert_eq!(buf, "/* pooled */ SELECT * FROM lol WHERE lol.id = $0");
+
+    let query = build_query!(
+        #![sink(Vec::new())]
+        "SELECT 1"
+    );
+    assert_eq!(query.sql, b"SELECT 1");
+}
+
+#[test]
+fn renders_into_fixed_buffer() {
+    let query = build_query!(
+        #![sink(sql_builder_test::FixedBuf::<32>::new())]
+        "SELECT " 1 ", " 2
+    );
+    assert_eq!(query.sql, "SELECT $0, $1");
+}
+
+#[test]
+#[should_panic(expected = "overflowed")]
+fn fixed_buffer_overflow_panics_on_build() {
+    build_query!(
+        #![sink(sql_builder_test::FixedBuf::<8>::new())]
+        "SELECT * FROM lol"
+    );
+}
+
+#[cfg(not(feature = "alloc"))]
+#[test]
+fn defaults_to_fixed_buffer_without_alloc() {
+    let query: sql_builder_test::Query<sql_builder_test::FixedBuf<1024>> =
+        build_query!("SELECT " 1);
+    assert_eq!(query.sql, "SELECT $0");
+}
+
+#[test]
+fn renders_dialect_placeholders() {
+    let query = build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        "SELECT * FROM lol WHERE a = " 1 " AND b = " 2
+    );
+    assert_eq!(query.sql, "SELECT * FROM lol WHERE a = $1 AND b = $2");
+
+    let query = build_query!(
+        #![dialect(sql_builder_test::Sqlite)]
+        "SELECT * FROM lol WHERE a = " 1
+    );
+    assert_eq!(query.sql, "SELECT * FROM lol WHERE a = ?1");
+}
+
+#[test]
+fn renders_in_lists() {
+    let ids = [3, 5, 8];
+    let query = build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        "SELECT * FROM lol WHERE id IN " in ids.iter() " AND a = " 1
+    )
+    .unwrap();
+    assert_eq!(
+        query.sql,
+        "SELECT * FROM lol WHERE id IN ($1, $2, $3) AND a = $4"
+    );
+    assert_eq!(query.args_count, 4);
+}
+
+#[test]
+fn try_build_reports_empty_in_list() {
+    let ids: [i32; 0] = [];
+    let result = build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        #![try_build]
+        "SELECT * FROM lol WHERE id IN " in ids.iter()
+    );
+    assert_eq!(
+        result.err(),
+        Some(sql_builder_test::BuildError::EmptyInList)
+    );
+
+    let query = build_query!(
+        #![dialect(sql_builder_test::Sqlite)]
+        #![try_build]
+        "SELECT * FROM lol WHERE id IN " in ids.iter()
+    )
+    .unwrap();
+    assert_eq!(query.sql, "SELECT * FROM lol WHERE id IN ()");
+}
+
+#[test]
+fn try_build_reports_too_many_parameters() {
+    // Big enough for a thousand placeholders without `alloc`, too.
+    let result = build_query!(
+        #![dialect(sql_builder_test::Sqlite)]
+        #![sink(sql_builder_test::FixedBuf::<8192>::default())]
+        #![try_build]
+        "SELECT * FROM lol WHERE id IN " in 0..1000
+    );
+    assert_eq!(
+        result.err(),
+        Some(sql_builder_test::BuildError::TooManyParameters {
+            count: 1000,
+            max: 999
+        })
+    );
+
+    let result = build_query!(
+        #![dialect(sql_builder_test::Sqlite)]
+        #![sink(sql_builder_test::FixedBuf::<8192>::default())]
+        #![try_build]
+        "SELECT * FROM lol WHERE id IN " in 0..999
+    );
+    assert!(result.is_ok());
+}
+
+#[test]
+fn in_lists_and_sets_return_results() {
+    let ids: Vec<i32> = vec![];
+    let result = build_query!("SELECT * FROM lol WHERE id IN " in ids);
+    assert_eq!(
+        result.err(),
+        Some(sql_builder_test::BuildError::EmptyInList)
+    );
+
+    let name: Option<&str> = None;
+    let result = build_query!(
+        "UPDATE lol" set { if let Some(name) = name { "name = " name } } " WHERE id = " 1
+    );
+    assert_eq!(result.err(), Some(sql_builder_test::BuildError::EmptySet));
+
+    // A `set` that always assigns something can't fail.
+    let query = build_query!(
+        "UPDATE lol" set { "seen = " 1 if let Some(name) = name { "name = " name } }
+    );
+    assert_eq!(query.sql, "UPDATE lol SET seen = $0");
+}
+
+#[test]
+#[should_panic(expected = "SQL sink overflowed")]
+fn build_panics_on_build_error() {
+    build_query!(
+        #![sink(sql_builder_test::FixedBuf::<8>::default())]
+        "SELECT * FROM lol"
+    );
+}
+
+fn find_lol(a: i32, b: Option<i32>) -> sql_builder_test::Query {
+    build_query!(
+        "SELECT * FROM lol WHERE a = " a
+        if let Some(b) = b { " AND b = " b }
+    )
+}
+
+#[test]
+fn fingerprints_ignore_values() {
+    assert_eq!(find_lol(1, None).fingerprint, find_lol(2, None).fingerprint);
+    assert_eq!(
+        find_lol(1, Some(1)).fingerprint,
+        find_lol(2, Some(3)).fingerprint
+    );
+    assert_ne!(
+        find_lol(1, None).fingerprint,
+        find_lol(1, Some(1)).fingerprint
+    );
+}
+
+#[test]
+fn fingerprints_ignore_whitespace_and_keyword_case() {
+    let b: Option<i32> = None;
+    let reformatted = build_query!(
+        "select *\n"
+        "    from lol\n"
+        "    where a = " 1
+        if let Some(b) = b {
+            "  and b = " b
+        }
+    );
+    assert_eq!(reformatted.fingerprint, find_lol(1, None).fingerprint);
+
+    let other = build_query!("SELECT * FROM lol WHERE b = " 1);
+    assert_ne!(other.fingerprint, find_lol(1, None).fingerprint);
+}
+
+#[test]
+fn fingerprints_ignore_lines_above() {
+    let before = build_query!("SELECT * FROM lol WHERE a = " 1);
+    // Lines added above an invocation
+    // shift it down, but don't change
+    // its fingerprint.
+    let after = build_query!("SELECT * FROM lol WHERE a = " 1);
+    assert_eq!(before.fingerprint, after.fingerprint);
+}
+
+#[test]
+fn fingerprints_differ_between_named_invocations() {
+    let first = build_query!(
+        #![fingerprint("first")]
+        "SELECT * FROM lol WHERE a = " 1
+    );
+    let second = build_query!(
+        #![fingerprint("second")]
+        "SELECT * FROM lol WHERE a = " 1
+    );
+    assert_eq!(
+        AsRef::<str>::as_ref(&first.sql),
+        AsRef::<str>::as_ref(&second.sql)
+    );
+    assert_ne!(first.fingerprint, second.fingerprint);
+    assert_ne!(
+        first.fingerprint,
+        build_query!("SELECT * FROM lol WHERE a = " 1).fingerprint
+    );
+}
+
+#[test]
+fn variants_differ_between_invocations() {
+    let select = build_query!("SELECT 1");
+    let delete = build_query!("DELETE FROM users");
+    assert_ne!(select.variant, delete.variant);
+    assert_eq!(select.variant, build_query!("SELECT 1").variant);
+}
+
+#[test]
+fn fingerprints_ignore_in_list_length() {
+    let ids = |n: i32| build_query!("SELECT * FROM lol WHERE id IN " in 0..n).unwrap();
+    assert_eq!(ids(1).fingerprint, ids(10).fingerprint);
+    assert_ne!(
+        ids(1).fingerprint,
+        build_query!("SELECT * FROM lol WHERE id IN (" 1 ")").fingerprint
+    );
+}
+
+#[test]
+fn fingerprint_displays_as_hex() {
+    use sql_builder_test::Fingerprint;
+
+    assert_eq!(Fingerprint::hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(Fingerprint(0xab).to_string(), "00000000000000ab");
+}
+
+fn search(name: Option<&str>, min_age: Option<i32>, roles: &[&str]) -> sql_builder_test::Query {
+    build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        "SELECT * FROM users"
+        where {
+            if let Some(name) = name { "name = " name }
+            if let Some(min_age) = min_age { "age >= " min_age }
+            or {
+                if roles.contains(&"admin") { "admin" }
+                if roles.contains(&"owner") { "owner_id = " 1 }
+            }
+        }
+        " ORDER BY id"
+    )
+}
+
+#[test]
+fn where_groups_join_present_predicates() {
+    assert_eq!(
+        search(Some("x"), Some(18), &[]).sql,
+        "SELECT * FROM users WHERE name = $1 AND age >= $2 ORDER BY id"
+    );
+    assert_eq!(
+        search(None, Some(18), &["admin", "owner"]).sql,
+        "SELECT * FROM users WHERE age >= $1 AND (admin OR owner_id = $2) ORDER BY id"
+    );
+    assert_eq!(
+        search(None, None, &["owner"]).sql,
+        "SELECT * FROM users WHERE (owner_id = $1) ORDER BY id"
+    );
+    assert_eq!(
+        search(None, None, &[]).sql,
+        "SELECT * FROM users ORDER BY id"
+    );
+}
+
+#[test]
+fn where_group_fingerprints_follow_the_predicates() {
+    assert_eq!(
+        search(Some("x"), None, &[]).fingerprint,
+        search(Some("y"), None, &[]).fingerprint
+    );
+    assert_ne!(
+        search(Some("x"), None, &[]).fingerprint,
+        search(None, Some(1), &[]).fingerprint
+    );
+    assert_ne!(
+        search(None, None, &[]).fingerprint,
+        search(Some("x"), None, &[]).fingerprint
+    );
+}
+
+fn patch_user(
+    id: i32,
+    name: Option<&str>,
+    age: Option<i32>,
+) -> Result<sql_builder_test::Query, sql_builder_test::BuildError> {
+    build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        #![try_build]
+        "UPDATE users"
+        set {
+            if let Some(name) = name { "name = " name }
+            if let Some(age) = age { "age = " age }
+            "updated_at = now()"
+        }
+        where { "id = " id }
+    )
+}
+
+#[test]
+fn set_groups_comma_join_assignments() {
+    assert_eq!(
+        patch_user(1, Some("x"), Some(2)).unwrap().sql,
+        "UPDATE users SET name = $1, age = $2, updated_at = now() WHERE id = $3"
+    );
+    assert_eq!(
+        patch_user(1, None, Some(2)).unwrap().sql,
+        "UPDATE users SET age = $1, updated_at = now() WHERE id = $2"
+    );
+}
+
+#[test]
+fn try_build_reports_empty_set() {
+    let set = |name: Option<&str>| {
+        build_query!(
+            #![try_build]
+            "UPDATE users"
+            set { if let Some(name) = name { "name = " name } }
+            " WHERE id = " 1
+        )
+    };
+    assert_eq!(
+        set(None).err(),
+        Some(sql_builder_test::BuildError::EmptySet)
+    );
+    assert_eq!(
+        set(Some("x")).unwrap().sql,
+        "UPDATE users SET name = $0 WHERE id = $1"
+    );
+}
+
+/// The manifest, CLI and plan snapshots render groups without the runtime, so
+/// check that they agree with it on every kind.
+#[cfg(feature = "alloc")]
+#[test]
+fn group_variants_render_like_the_runtime() {
+    use std::collections::BTreeSet;
+
+    use sql_builder_syntax::{blocks, parse, variants};
+
+    macro_rules! check {
+        ($a:ident, $b:ident: $($body:tt)*) => {{
+            let ast: parse::BuilderAST = syn::parse_str(stringify!($($body)*)).unwrap();
+            let blocks = blocks::create_blocks(
+                ast.constituents,
+                blocks::Parent::root(),
+                &mut blocks::Counter::new(),
+            )
+        .unwrap();
+            let expected: BTreeSet<String> = variants::enumerate(&blocks, 16)
+                .0
+                .iter()
+                .map(|variant| variant.render(variants::PlaceholderStyle::DollarFromZero).sql)
+                .collect();
+            let mut actual = BTreeSet::new();
+            for &($a, $b) in &[(false, false), (false, true), (true, false), (true, true)] {
+                if let Ok(query) = build_query!(#![try_build] $($body)*) {
+                    actual.insert(query.sql);
+                }
+            }
+            assert_eq!(actual, expected);
+        }};
+    }
+
+    check!(a, b: "SELECT * FROM t" where { if a { "a" } if b { "b" } });
+    check!(a, b: "SELECT * FROM t WHERE " or { if a { "a" } if b { "b" } });
+    check!(a, b: "UPDATE t" set { if a { "a = 1" } if b { "b = 1" } });
+    check!(a, b: "SELECT * FROM t" where { "c" or { if a { "a" } if b { "b" } } });
+    assert_eq!(parse::MAX_GROUP_DEPTH, sql_builder_test::MAX_GROUP_DEPTH);
+}
+
+#[cfg(feature = "alloc")]
+mod splice {
+    use sql_builder_test::{build_query, sql_fragment, BuildError, Postgres, SqlFragment, Sqlite};
+
+    #[cfg(feature = "splice-queries")]
+    #[test]
+    fn renumbers_nested_query_placeholders() {
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM a WHERE x = " 1 " AND id IN ("
+            ..build_query!("SELECT id FROM b WHERE y = " 2 " AND z = " 3)
+            ") AND w = " 4
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM a WHERE x = $1 AND id IN (SELECT id FROM b WHERE y = $2 AND z = $3) AND w = $4"
+        );
+        assert_eq!(query.args_count, 4);
+        assert_eq!(query.args_size, 4 * 4);
+        assert_eq!(query.placeholders.len(), 4);
+    }
+
+    #[cfg(feature = "splice-queries")]
+    #[test]
+    fn splices_only_what_a_query_rendered() {
+        let mut buf = String::from("/* pooled */ ");
+        let inner = build_query!(
+            #![sink(&mut buf)]
+            "SELECT id FROM b WHERE y = " 2
+        );
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM a WHERE x = " 1 " AND id IN (" ..inner ")"
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM a WHERE x = $1 AND id IN (SELECT id FROM b WHERE y = $2)"
+        );
+    }
+
+    #[cfg(feature = "splice-queries")]
+    #[test]
+    fn splices_a_query_built_earlier() {
+        let active = |since: i64| {
+            build_query!(
+                #![dialect(Sqlite)]
+                "SELECT user_id FROM sessions WHERE seen > " since
+            )
+        };
+        let query = build_query!(
+            #![dialect(Sqlite)]
+            "SELECT * FROM users WHERE org = " 7 " AND id IN (" ..active(10) ")"
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE org = ?1 AND id IN (SELECT user_id FROM sessions WHERE seen > ?2)"
+        );
+        assert_eq!(query.args_count, 2);
+        assert_ne!(
+            query.fingerprint,
+            build_query!("SELECT * FROM users WHERE org = " 7).fingerprint
+        );
+    }
+
+    fn filters(name: Option<&str>, ids: &[i32], min_age: Option<i32>) -> SqlFragment {
+        let mut filter = SqlFragment::empty();
+        if let Some(name) = name {
+            filter = filter.and(sql_fragment!("name = " name " OR nick = " name));
+        }
+        if !ids.is_empty() {
+            filter = filter.and(sql_fragment!("id IN " in ids));
+        }
+        if let Some(min_age) = min_age {
+            filter = filter.and(sql_fragment!("age >= " min_age));
+        }
+        filter
+    }
+
+    #[test]
+    fn combines_fragments() {
+        let filter = filters(Some("x"), &[1, 2], Some(18));
+        assert_eq!(filter.args_count(), 5);
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users WHERE org = " 7 " AND " ..filter
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE org = $1 AND (((name = $2 OR nick = $3) AND (id IN ($4, $5))) AND (age >= $6))"
+        );
+        assert_eq!(query.args_count, 6);
+    }
+
+    #[test]
+    fn and_or_skip_empty_fragments() {
+        let filter = filters(None, &[], Some(18));
+        let query = build_query!(#![dialect(Sqlite)] "SELECT * FROM users WHERE " ..filter);
+        assert_eq!(query.sql, "SELECT * FROM users WHERE age >= ?1");
+
+        let either = SqlFragment::empty()
+            .or(sql_fragment!("a = " 1))
+            .or(sql_fragment!("b = " 2));
+        let query =
+            build_query!(#![dialect(Sqlite)] "SELECT * FROM t WHERE " ..&either " AND c = " 3);
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM t WHERE ((a = ?1) OR (b = ?2)) AND c = ?3"
+        );
+    }
+
+    #[test]
+    fn joins_fragments() {
+        let columns = ["a", "b"]
+            .iter()
+            .map(|value| sql_fragment!("coalesce(" value ", '')"));
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT concat(" ..SqlFragment::join(", ", columns) ") FROM t"
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT concat(coalesce($1, ''), coalesce($2, '')) FROM t"
+        );
+    }
+
+    #[test]
+    fn splices_are_group_items() {
+        let filter = filters(None, &[], Some(18));
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users"
+            where { "deleted_at IS NULL" ..filter "org = " 7 }
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE deleted_at IS NULL AND age >= $1 AND org = $2"
+        );
+
+        let empty = SqlFragment::empty();
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users" where { ..empty } or { "a" ..SqlFragment::empty() }
+        );
+        assert_eq!(query.sql, "SELECT * FROM users(a)");
+    }
+
+    #[test]
+    fn fragment_errors_surface_when_building() {
+        let empty: [i32; 0] = [];
+        let fragment = sql_fragment!("id IN " in empty.iter());
+        let result = build_query!(
+            #![dialect(Postgres)]
+            #![try_build]
+            "SELECT * FROM t WHERE " ..fragment
+        );
+        assert_eq!(result.err(), Some(BuildError::EmptyInList));
+    }
+
+    #[test]
+    fn empty_in_lists_in_fragments_follow_the_query_dialect() {
+        let empty: [i32; 0] = [];
+        let fragment = sql_fragment!("id IN " in empty.iter());
+        let query = build_query!(
+            #![dialect(Sqlite)]
+            "SELECT * FROM t WHERE " ..&fragment
+        );
+        assert_eq!(query.sql, "SELECT * FROM t WHERE id IN ()");
+
+        let result = build_query!(
+            #![dialect(Postgres)]
+            #![try_build]
+            "SELECT * FROM t WHERE " ..fragment
+        );
+        assert_eq!(result.err(), Some(BuildError::EmptyInList));
+    }
+
+    #[test]
+    fn fragment_branches_fold_into_the_variant() {
+        let query = |admin: bool| {
+            let fragment = sql_fragment!(if admin { "admin" } else { "owner_id = " 1 });
+            build_query!(#![dialect(Postgres)] "SELECT * FROM t WHERE " ..fragment)
+        };
+        assert_ne!(query(true).variant, query(false).variant);
+        assert_eq!(query(true).variant, query(true).variant);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod keyset {
+    use sql_builder_test::{
+        build_query, sort_key, CursorError, Generic, Keyset, Postgres, SortKey, Sqlite,
+    };
+
+    const BY_ID: &[SortKey] = &[sort_key!(asc "id")];
+    const BY_NAME: &[SortKey] = &[sort_key!(asc "name"), sort_key!(asc "id")];
+    const NEWEST: &[SortKey] = &[sort_key!(desc "created_at"), sort_key!(asc "id")];
+
+    #[test]
+    fn first_page_only_orders_and_limits() {
+        let page = Keyset::<(i64,)>::new(BY_ID, 20);
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users" where { "org = " 7 ..page.predicate() } ..page.order_by()
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE org = $1 ORDER BY id ASC LIMIT $2"
+        );
+        assert_eq!(query.args_count, 2);
+    }
+
+    #[test]
+    fn compares_row_values() {
+        let page = Keyset::<(String, i64)>::new(BY_NAME, 20).after_keys(("kim".into(), 3));
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users" where { "org = " 7 ..page.predicate() } ..page.order_by()
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE org = $1 AND (name, id) > ($2, $3) ORDER BY name ASC, id ASC LIMIT $4"
+        );
+        assert_eq!(query.args_count, 4);
+    }
+
+    #[test]
+    fn expands_without_row_values() {
+        let page = Keyset::<(String, i64)>::new(BY_NAME, 20).after_keys(("kim".into(), 3));
+        let query = build_query!(
+            #![dialect(Generic)]
+            "SELECT * FROM users" where { ..page.predicate() } ..page.order_by()
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE (name > $0 OR (name = $1 AND id > $2)) ORDER BY name ASC, id ASC LIMIT $3"
+        );
+        assert_eq!(query.args_count, 4);
+    }
+
+    #[test]
+    fn expands_mixed_directions() {
+        let page = Keyset::<(i64, i64)>::new(NEWEST, 10).after_keys((1_700_000_000, 42));
+        let query = build_query!(
+            #![dialect(Sqlite)]
+            "SELECT * FROM posts" where { ..page.predicate() } ..page.order_by()
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM posts WHERE (created_at < ?1 OR (created_at = ?2 AND id > ?3)) ORDER BY created_at DESC, id ASC LIMIT ?4"
+        );
+    }
+
+    #[test]
+    fn single_key_compares_directly() {
+        let page = Keyset::<(i64,)>::new(BY_ID, 20).after_keys((9,));
+        let query = build_query!(
+            #![dialect(Sqlite)]
+            "SELECT * FROM users" where { ..page.predicate() } ..page.order_by()
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE id > ?1 ORDER BY id ASC LIMIT ?2"
+        );
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let page = Keyset::<(String, i64)>::new(BY_NAME, 20);
+        let cursor = page.cursor(&("o'brien, jr.".into(), -3));
+        assert!(cursor
+            .bytes()
+            .all(|byte| byte.is_ascii_hexdigit() || byte == b'.'));
+
+        let next = page.clone().after(Some(&cursor)).unwrap();
+        let query =
+            build_query!(#![dialect(Postgres)] "SELECT * FROM users" where { ..next.predicate() });
+        let expected =
+            Keyset::<(String, i64)>::new(BY_NAME, 20).after_keys(("o'brien, jr.".into(), -3));
+        let direct = build_query!(#![dialect(Postgres)] "SELECT * FROM users" where { ..expected.predicate() });
+        assert_eq!(query.sql, direct.sql);
+        assert_eq!(next.cursor(&("o'brien, jr.".into(), -3)), cursor);
+
+        assert!(page.clone().after(None).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let page = Keyset::<(String, i64)>::new(BY_NAME, 20);
+        let one_value = Keyset::<(i64,)>::new(BY_ID, 20).cursor(&(3,));
+        let not_a_number = page.cursor(&("a".into(), 0)).replace(".30", ".78");
+        for cursor in ["", "zz.33", "6b.3", &one_value, &not_a_number] {
+            assert_eq!(
+                page.clone().after(Some(cursor)).err(),
+                Some(CursorError),
+                "{}",
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_cursor_values() {
+        let first = Keyset::<(i64, i64)>::new(NEWEST, 10).after_keys((1, 2));
+        let second = Keyset::<(i64, i64)>::new(NEWEST, 10).after_keys((3, 4));
+        let start = Keyset::<(i64, i64)>::new(NEWEST, 10);
+        let query = |page: &Keyset<(i64, i64)>| {
+            build_query!("SELECT * FROM posts" where { ..page.predicate() } ..page.order_by())
+                .fingerprint
+        };
+        assert_eq!(query(&first), query(&second));
+        assert_ne!(query(&first), query(&start));
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod batch {
+    use sql_builder_test::{build_query, Postgres, Sqlite};
+
+    fn sqlite_pairs(rows: usize) -> Vec<sql_builder_test::Query> {
+        build_query!(
+            #![dialect(Sqlite)]
+            "INSERT INTO t (a, b) VALUES "
+            for (a, b) in (0..rows).map(|i| (i, i)) {
+                "(" a ", " b ")"
+            }
+        )
+    }
+
+    #[test]
+    fn renders_values_list() {
+        let queries = build_query!(
+            #![dialect(Postgres)]
+            "INSERT INTO t (a, b) VALUES "
+            for (a, b) in vec![(1, "x"), (2, "y")] {
+                "(" a ", " b ")"
+            }
+            " RETURNING id"
+        );
+        assert_eq!(queries.len(), 1);
+        assert_eq!(
+            queries[0].sql,
+            "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4) RETURNING id"
+        );
+        assert_eq!(queries[0].args_count, 4);
+    }
+
+    #[test]
+    fn chunks_share_a_fingerprint() {
+        let queries = sqlite_pairs(500);
+        assert_eq!(queries[0].fingerprint, queries[1].fingerprint);
+        assert_eq!(queries[0].fingerprint, sqlite_pairs(3)[0].fingerprint);
+    }
+
+    #[test]
+    fn no_rows_means_no_queries() {
+        assert!(sqlite_pairs(0).is_empty());
+    }
+
+    #[test]
+    fn try_build_collects_chunks() {
+        let queries = build_query!(
+            #![dialect(Sqlite)]
+            #![try_build]
+            "INSERT INTO t (a) VALUES "
+            for a in 0..1000 {
+                "(" a ")"
+            }
+        )
+        .unwrap();
+        assert_eq!(queries.len(), 2);
+    }
+
+    #[test]
+    fn try_build_fails_when_no_row_fits() {
+        let ids: Vec<i32> = (0..999).collect();
+        let result = build_query!(
+            #![dialect(Sqlite)]
+            #![try_build]
+            "WITH skip AS (SELECT id FROM s WHERE id IN " in ids.iter() ") "
+            "INSERT INTO t (a) VALUES "
+            for a in 0..3 {
+                "(" a ")"
+            }
+        );
+        assert_eq!(
+            result.err(),
+            Some(sql_builder_test::BuildError::TooManyParameters {
+                count: 1000,
+                max: 999
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "query has 1000 parameters, but the dialect allows at most 999")]
+    fn build_panics_when_no_row_fits() {
+        let ids: Vec<i32> = (0..999).collect();
+        let skip = sql_builder_test::sql_fragment!("SELECT id FROM s WHERE id IN " in ids.iter());
+        build_query!(
+            #![dialect(Sqlite)]
+            "WITH skip AS (" ..&skip ") "
+            "INSERT INTO t (a) VALUES "
+            for a in 0..3 {
+                "(" a ")"
+            }
+        );
+    }
+
+    #[test]
+    fn chunks_at_the_parameter_limit() {
+        // 499 rows of two parameters is 998, just under SQLite's 999.
+        let queries = sqlite_pairs(499);
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].args_count, 998);
+
+        let queries = sqlite_pairs(500);
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].args_count, 998);
+        assert_eq!(queries[1].args_count, 2);
+        assert_eq!(queries[1].sql, "INSERT INTO t (a, b) VALUES (?1, ?2)");
+    }
+
+    #[test]
+    fn reserves_parameters_around_the_rows() {
+        let tenant = 1;
+        let flag: Option<i32> = Some(1);
+        let queries = build_query!(
+            #![dialect(Postgres)]
+            "WITH tenant AS (SELECT " tenant " AS id) "
+            "INSERT INTO t (a, b, c) VALUES "
+            for a in 0..21844 {
+                "(" a ", " a ", " a ")"
+            }
+            if let Some(flag) = flag {
+                " ON CONFLICT DO UPDATE SET flag = " flag
+            }
+        );
+        // 1 + 21844 * 3 + 1 == 65534
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].args_count, 65534);
+
+        let queries = build_query!(
+            #![dialect(Postgres)]
+            "WITH tenant AS (SELECT " tenant " AS id) "
+            "INSERT INTO t (a, b, c) VALUES "
+            for a in 0..21845 {
+                "(" a ", " a ", " a ")"
+            }
+            if let Some(flag) = flag {
+                " ON CONFLICT DO UPDATE SET flag = " flag
+            }
+        );
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].args_count, 65534);
+        assert_eq!(queries[1].args_count, 5);
+        assert!(queries[1]
+            .sql
+            .starts_with("WITH tenant AS (SELECT $1 AS id) "));
+    }
+
+    #[test]
+    fn binds_around_the_rows_once() {
+        let org = String::from("acme");
+        let source = String::from("import");
+        let skip = String::from("0");
+        let queries = build_query!(
+            #![dialect(Sqlite)]
+            "INSERT INTO t (org, a, source) SELECT " org ", a, " source " FROM (VALUES "
+            for a in 0..1000 {
+                "(" a ")"
+            }
+            ") v WHERE a <> " skip
+        );
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].args_count, 999);
+        assert_eq!(queries[1].args_count, 7);
+        assert_eq!(
+            queries[1].sql,
+            "INSERT INTO t (org, a, source) SELECT ?1, a, ?2 FROM (VALUES (?3), (?4), (?5), (?6)) v WHERE a <> ?7"
+        );
+    }
+
+    #[test]
+    fn decides_branches_around_the_rows_once() {
+        let source: Option<String> = Some("import".into());
+        let conflict: Option<String> = Some("skip".into());
+        let checks = std::cell::Cell::new(0);
+        let check = |result: bool| {
+            checks.set(checks.get() + 1);
+            result
+        };
+        let queries = build_query!(
+            #![dialect(Sqlite)]
+            "INSERT INTO t (a, source) SELECT a, "
+            if let Some(source) = source {
+                source
+            } else if check(true) {
+                "NULL"
+            }
+            " FROM (VALUES "
+            for a in 0..1000 {
+                "(" a ")"
+            }
+            ") v"
+            if check(false) {
+                " WHERE a > 0"
+            } else if let Some(conflict) = conflict {
+                " ON CONFLICT DO " conflict
+            }
+        );
+        assert_eq!(checks.get(), 1);
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].args_count, 999);
+        assert_eq!(
+            queries[1].sql,
+            "INSERT INTO t (a, source) SELECT a, ?1 FROM (VALUES (?2), (?3), (?4)) v ON CONFLICT DO ?5"
+        );
+        assert_eq!(
+            queries[1].args.0,
+            [
+                sql_builder_test::Value::Text("import".into()),
+                sql_builder_test::Value::I32(997),
+                sql_builder_test::Value::I32(998),
+                sql_builder_test::Value::I32(999),
+                sql_builder_test::Value::Text("skip".into()),
+            ]
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod args {
+    use std::any::Any;
+
+    use sql_builder_test::{
+        build_query, sql_fragment, CustomValue, Encode, FromValue, Native, PackedArgs, Postgres,
+        SqlFragment, Value, ValueRef,
+    };
+
+    #[test]
+    fn collects_values_in_placeholder_order() {
+        let name = String::from("kim");
+        let ids = [3_u32, 4];
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users WHERE name = " name " AND id IN " in ids.iter() " AND deleted = " false
+        )
+        .unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE name = $1 AND id IN ($2, $3) AND deleted = $4"
+        );
+        assert_eq!(
+            query.args.0,
+            [
+                Value::Text("kim".into()),
+                Value::I64(3),
+                Value::I64(4),
+                Value::Bool(false),
+            ]
+        );
+    }
+
+    #[test]
+    fn binds_none_as_null() {
+        let nickname: Option<&str> = None;
+        let query = build_query!("UPDATE users SET nickname = " nickname);
+        assert_eq!(query.args.0, [Value::Null]);
+    }
+
+    #[test]
+    fn splicing_appends_values() {
+        let name = "kim";
+        let filter: SqlFragment = sql_fragment!("org = " 7_i64);
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users WHERE name = " name " AND " ..filter
+        );
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM users WHERE name = $1 AND org = $2"
+        );
+        assert_eq!(query.args.0, [Value::Text("kim".into()), Value::I64(7)]);
+    }
+
+    #[test]
+    fn packs_values_into_bytes() {
+        let text = "ab";
+        let query = build_query!(
+            #![args(PackedArgs)]
+            "SELECT " 1_i16 ", " text ", " None::<i32> ", " u64::MAX ", " &b"\x00\xff"[..]
+        );
+        assert_eq!(query.sql, "SELECT $0, $1, $2, $3, $4");
+        assert_eq!(
+            query.args.as_bytes(),
+            [
+                &[2, 1, 0][..],
+                &[8, 2, 0, 0, 0, b'a', b'b'],
+                &[0],
+                &[5, 255, 255, 255, 255, 255, 255, 255, 255],
+                &[9, 2, 0, 0, 0, 0, 255],
+            ]
+            .concat()
+        );
+        assert_eq!(
+            query.args.iter().collect::<Vec<_>>(),
+            [
+                ValueRef::I16(1),
+                ValueRef::Text("ab"),
+                ValueRef::Null,
+                ValueRef::U64(u64::MAX),
+                ValueRef::Bytes(&[0, 255]),
+            ]
+        );
+    }
+
+    #[derive(Debug)]
+    struct Uuid([u8; 16]);
+
+    impl CustomValue for Uuid {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn fallback(&self) -> ValueRef<'_> {
+            ValueRef::Bytes(&self.0)
+        }
+    }
+
+    impl Encode for Uuid {
+        fn encode(&self) -> ValueRef<'_> {
+            ValueRef::Custom(self)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Driver {
+        Null,
+        Integer(i64),
+        Text(String),
+        Uuid([u8; 16]),
+        Other,
+    }
+
+    impl FromValue for Driver {
+        fn from_value(value: ValueRef<'_>) -> Self {
+            match value {
+                ValueRef::Custom(value) => match value.as_any().downcast_ref::<Uuid>() {
+                    Some(uuid) => Driver::Uuid(uuid.0),
+                    None => Driver::Other,
+                },
+                ValueRef::Null => Driver::Null,
+                ValueRef::I16(value) => Driver::Integer(value.into()),
+                ValueRef::I32(value) => Driver::Integer(value.into()),
+                ValueRef::I64(value) => Driver::Integer(value),
+                ValueRef::Text(value) => Driver::Text(value.into()),
+                _ => Driver::Other,
+            }
+        }
+    }
+
+    #[test]
+    fn converts_into_driver_values() {
+        let name = "kim";
+        let query = build_query!(
+            #![args(Native<Driver>)]
+            "INSERT INTO users VALUES (" 1_i32 ", " name ", " None::<bool> ", " 2.5_f64 ")"
+        );
+        assert_eq!(
+            query.args.0,
+            [
+                Driver::Integer(1),
+                Driver::Text("kim".into()),
+                Driver::Null,
+                Driver::Other,
+            ]
+        );
+    }
+
+    #[test]
+    fn binds_custom_values() {
+        let id = Uuid([7; 16]);
+        let query = build_query!(
+            #![args(Native<Driver>)]
+            "SELECT * FROM users WHERE id = " id
+        );
+        assert_eq!(query.args.0, [Driver::Uuid([7; 16])]);
+
+        let query = build_query!("SELECT * FROM users WHERE id = " Uuid([7; 16]));
+        assert_eq!(query.args.0, [Value::Bytes(vec![7; 16])]);
+
+        let query = build_query!(#![args(PackedArgs)] "SELECT " Uuid([7; 16]));
+        assert_eq!(
+            query.args.iter().collect::<Vec<_>>(),
+            [ValueRef::Bytes(&[7; 16])]
+        );
+    }
+
+    #[test]
+    fn discards_values_into_unit() {
+        let query = build_query!(#![args(())] "SELECT " 1_i32);
+        assert_eq!(query.sql, "SELECT $0");
+        assert_eq!(query.args_count, 1);
+        assert_eq!(query.args_size, 4);
+    }
+
+    #[test]
+    fn sizes_values_as_encoded() {
+        let name = String::from("kim");
+        let by_value = build_query!("SELECT " 1_i64 ", " name.clone() ", " None::<i32>);
+        let by_ref = build_query!("SELECT " &1_i64 ", " &name ", " &None::<i32>);
+        assert_eq!(by_value.args_size, 8 + 3);
+        assert_eq!(by_ref.args_size, by_value.args_size);
+
+        let query = build_query!("SELECT " Uuid([7; 16]));
+        assert_eq!(query.args_size, 16);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod mock {
+    use sql_builder_test::{build_query, MockError, MockExecutor, Postgres, Value};
+
+    fn find_user(mock: &mut MockExecutor, name: &str) -> Result<Option<i64>, MockError> {
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT id FROM users WHERE name = " name
+        );
+        let rows = mock.run(&query)?.rows;
+        Ok(rows.first().and_then(|row| match row[0] {
+            Value::I64(id) => Some(id),
+            _ => None,
+        }))
+    }
+
+    #[test]
+    fn records_queries_and_returns_scripted_rows() {
+        let mut mock = MockExecutor::new();
+        mock.returns_rows(vec![vec![Value::I64(3)]]);
+        assert_eq!(find_user(&mut mock, "kim"), Ok(Some(3)));
+        assert_eq!(find_user(&mut mock, "lee"), Ok(None));
+        let fingerprint = mock.executed()[0].fingerprint;
+        mock.expect_sql(
+            "SELECT id FROM users WHERE name = $1",
+            &[Value::Text("kim".into())],
+        )
+        .expect_fingerprint(fingerprint, &[Value::Text("lee".into())])
+        .expect_done();
+    }
+
+    #[test]
+    fn returns_scripted_errors() {
+        let mut mock = MockExecutor::new();
+        mock.fails("connection reset").returns_affected(2);
+        assert_eq!(
+            find_user(&mut mock, "kim"),
+            Err(MockError("connection reset".into()))
+        );
+        let name = "kim";
+        let query = build_query!("DELETE FROM users WHERE name = " name);
+        assert_eq!(mock.run(&query).unwrap().affected, 2);
+        assert_eq!(mock.executed().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected args")]
+    fn expectations_check_args() {
+        let mut mock = MockExecutor::new();
+        find_user(&mut mock, "kim").unwrap();
+        mock.expect_sql(
+            "SELECT id FROM users WHERE name = $1",
+            &[Value::Text("lee".into())],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unchecked query")]
+    fn expect_done_reports_unchecked_queries() {
+        let mut mock = MockExecutor::new();
+        find_user(&mut mock, "kim").unwrap();
+        mock.expect_done();
+    }
+
+    #[test]
+    fn expectations_match_variants() {
+        let mut mock = MockExecutor::new();
+        let by = |mock: &mut MockExecutor, id: Option<i64>| {
+            let query = build_query!(
+                "SELECT * FROM users" where { if let Some(id) = id { "id = " id } }
+            );
+            mock.run(&query).unwrap();
+            query.variant
+        };
+        let all = by(&mut mock, None);
+        let one = by(&mut mock, Some(4));
+        assert_ne!(all, one);
+        mock.expect_variant(all, &[])
+            .expect_variant(one, &[Value::I64(4)])
+            .expect_done();
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod wire {
+    use sql_builder_test::{
+        build_query, write_execute, write_sync, PackedArgs, Postgres, WireError,
+    };
+
+    const PARSE: &[u8] =
+        b"P\0\0\0\x54\0SELECT * FROM users WHERE id = $1 AND name = $2 AND deleted = $3\0\
+        \0\x03\0\0\0\x17\0\0\0\x19\0\0\0\0";
+    const BIND: &[u8] = b"B\0\0\0\x25\0\0\0\x03\0\x01\0\x01\0\x01\
+        \0\x03\0\0\0\x04\0\0\0\x07\0\0\0\x03kim\xff\xff\xff\xff\0\0";
+    const EXECUTE: &[u8] = b"E\0\0\0\x09\0\0\0\0\0";
+    const SYNC: &[u8] = b"S\0\0\0\x04";
+
+    #[test]
+    fn writes_parse_bind_execute() {
+        let (id, name, deleted) = (7_i32, "kim", None::<bool>);
+        let query = build_query!(
+            #![dialect(Postgres)]
+            "SELECT * FROM users WHERE id = " id " AND name = " name " AND deleted = " deleted
+        );
+        let mut out = vec![];
+        query.write_extended(&mut out).unwrap();
+        write_sync(&mut out);
+        assert_eq!(out, [PARSE, BIND, EXECUTE, SYNC].concat());
+    }
+
+    #[test]
+    fn writes_named_statements_and_portals() {
+        let bytes: &[u8] = &[1, 2];
+        let query = build_query!(
+            #![dialect(Postgres)]
+            #![args(PackedArgs)]
+            "SELECT " 12345678901234567890_u64 ", " 1.5_f64 ", " bytes
+        );
+        let mut out = vec![];
+        query.write_parse("by_id", &mut out).unwrap();
+        assert_eq!(
+            out,
+            &b"P\0\0\0\x2aby_id\0SELECT $1, $2, $3\0\0\x03\0\0\x06\xa4\0\0\x02\xbd\0\0\0\x11"[..]
+        );
+        out.clear();
+        query.write_bind("p1", "by_id", &mut out).unwrap();
+        assert_eq!(
+            out,
+            &b"B\0\0\0\x43p1\0by_id\0\0\x03\0\0\0\x01\0\x01\0\x03\
+                \0\0\0\x1412345678901234567890\
+                \0\0\0\x08\x3f\xf8\0\0\0\0\0\0\
+                \0\0\0\x02\x01\x02\0\0"[..]
+        );
+        out.clear();
+        write_execute("p1", 100, &mut out).unwrap();
+        assert_eq!(out, &b"E\0\0\0\x0bp1\0\0\0\0\x64"[..]);
+    }
+
+    #[test]
+    fn leaves_the_buffer_alone_on_errors() {
+        let query = build_query!(#![dialect(Postgres)] "SELECT " 1_i32);
+        let mut out = vec![1, 2, 3];
+        assert_eq!(query.write_parse("by\0id", &mut out), Err(WireError::Nul));
+        assert_eq!(out, [1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_other_placeholder_styles() {
+        let generic = build_query!("SELECT " 1_i32);
+        let sqlite = build_query!(#![dialect(sql_builder_test::Sqlite)] "SELECT " 1_i32);
+        let mut out = vec![];
+        assert_eq!(
+            generic.write_extended(&mut out),
+            Err(WireError::Placeholders)
+        );
+        assert_eq!(
+            sqlite.write_parse("", &mut out),
+            Err(WireError::Placeholders)
+        );
+        assert!(out.is_empty());
+    }
+}
+
+#[cfg(feature = "fragments")]
+#[test]
+fn maps_sql_back_to_fragments() {
+    use sql_builder_test::FragmentKind;
+
+    let foo = Some(42_i32);
+    let query = build_query!(
+        #![dialect(sql_builder_test::Postgres)]
+        "SELECT * FROM lol WHERE "
+        if let Some(i) = foo {
+            "lol.id = " i
+        } else {
+            "TRUE"
+        }
+    );
+    assert_eq!(query.sql, "SELECT * FROM lol WHERE lol.id = $1");
+
+    let kinds: Vec<_> = query
+        .fragments
+        .iter()
+        .map(|fragment| (fragment.range.clone(), fragment.origin.unwrap().kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (0..24, FragmentKind::Literal(0)),
+            (24..33, FragmentKind::Literal(1)),
+            (33..35, FragmentKind::Bind(0)),
+        ]
+    );
+
+    let origin = query.fragments.at(34).unwrap().origin.unwrap();
+    assert_eq!(origin.location.file, file!());
+    assert_eq!(origin.location.line, line!() - 23);
+    assert_eq!(origin.location.column, 25);
+    assert_eq!(origin.path, &[1, 0, 0]);
+
+    assert!(query.fragments.at(35).is_none());
+}
+
+#[cfg(feature = "fragments")]
+#[test]
+fn locates_error_positions_in_the_source() {
+    let foo: Option<i32> = None;
+    let query = build_query!(
+        "SELECT * FROM lól WHERE "
+        if let Some(i) = foo {
+            "lol.id = " i
+        } else if foo.is_none() {
+            "FALSE"
+        } else {
+            "TRUE"
+        }
+    );
+    // Invocations are located by their first token.
+    let call_line = line!() - 10;
+    assert_eq!(query.sql, "SELECT * FROM lól WHERE FALSE");
+
+    // Postgres positions are 1-based and count characters, not bytes.
+    let span = query.locate(25).unwrap();
+    assert_eq!(span.sql, "FALSE");
+    assert_eq!(span.origin.call_site.line, call_line);
+    assert_eq!(
+        span.origin.branches,
+        &[sql_builder_test::BranchStep {
+            keyword: "else if",
+            line: call_line + 3,
+        }]
+    );
+    assert_eq!(
+        span.to_string(),
+        format!(
+            "`FALSE` in the `else if` branch at {}:{}:13 (build_query! at {}:{}:9)",
+            file!(),
+            call_line + 4,
+            file!(),
+            call_line
+        )
+    );
+    #[cfg(feature = "tracing")]
+    assert_eq!(query.call_site, Some(&span.origin.call_site));
+
+    assert_eq!(query.locate(1).unwrap().sql, "SELECT * FROM lól WHERE ");
+    assert!(query.locate(0).is_none());
+    assert!(query.locate(30).is_none());
+}
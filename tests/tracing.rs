@@ -0,0 +1,193 @@
+#![cfg(all(feature = "tracing", feature = "std"))]
+
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use sql_builder_test::{build_query, Query};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Records every span, span update and event as a line of `name field=value ...`.
+#[derive(Clone, Default)]
+struct Capture {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        write!(self.0, " {}={:?}", field.name(), value).unwrap();
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields(format!("span {}", span.metadata().name()));
+        span.record(&mut fields);
+        let mut lines = self.lines.lock().unwrap();
+        lines.push(fields.0);
+        Id::from_u64(lines.len() as u64)
+    }
+
+    fn record(&self, _: &Id, values: &Record<'_>) {
+        let mut fields = Fields("record".to_owned());
+        values.record(&mut fields);
+        self.lines.lock().unwrap().push(fields.0);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(format!("event {}", event.metadata().level()));
+        event.record(&mut fields);
+        self.lines.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn capture(f: impl FnOnce()) -> Vec<String> {
+    let capture = Capture::default();
+    tracing::subscriber::with_default(capture.clone(), f);
+    let lines = capture.lines.lock().unwrap();
+    lines.clone()
+}
+
+fn find_line() -> u32 {
+    line!() + 5
+}
+
+fn find(a: i32, b: Option<i32>) -> Query {
+    build_query!(
+        "SELECT * FROM lol WHERE a = " a
+        if let Some(b) = b { " AND b = " b }
+    )
+}
+
+fn field<'a>(line: &'a str, name: &str) -> &'a str {
+    let start = line.find(&format!(" {}=", name)).unwrap() + name.len() + 2;
+    line[start..].split(' ').next().unwrap()
+}
+
+#[test]
+fn reports_built_queries() {
+    let mut query = None;
+    let lines = capture(|| query = Some(find(1, Some(2))));
+    let query = query.unwrap();
+
+    assert_eq!(lines.len(), 1);
+    let line = &lines[0];
+    assert!(
+        line.starts_with("event DEBUG message=built query"),
+        "{}",
+        line
+    );
+    // Located by the first token of the invocation, as in the query manifest.
+    assert_eq!(
+        field(line, "call_site"),
+        format!("tests/tracing.rs:{}:9", find_line())
+    );
+    assert_eq!(field(line, "fingerprint"), query.fingerprint.to_string());
+    assert_eq!(field(line, "variant"), format!("{:016x}", query.variant));
+    assert_eq!(field(line, "args_count"), "2");
+    assert_eq!(field(line, "sql_len"), query.sql.len().to_string());
+    assert_eq!(query.call_site.unwrap().file, "tests/tracing.rs");
+}
+
+#[test]
+fn variants_tell_branches_apart() {
+    assert_eq!(find(1, None).variant, find(2, None).variant);
+    assert_ne!(find(1, None).variant, find(1, Some(2)).variant);
+}
+
+#[test]
+fn reports_build_errors() {
+    let lines = capture(|| {
+        let result = build_query!(
+            #![dialect(sql_builder_test::Postgres)]
+            #![try_build]
+            "SELECT * FROM lol WHERE id IN " in Vec::<i32>::new()
+        );
+        assert!(result.is_err());
+    });
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("event WARN message=failed to build query"));
+    assert!(lines[0].ends_with("error=empty IN list is not supported by the dialect"));
+}
+
+#[test]
+fn records_executions() {
+    let query = find(1, None);
+    let lines = capture(|| {
+        let execution = query.execution();
+        execution.in_scope(|| {});
+        execution.finish(3);
+
+        query.execution().fail(&"connection reset");
+    });
+
+    assert_eq!(lines.len(), 6, "{:#?}", lines);
+    assert!(lines[0].starts_with("span query call_site="));
+    assert_eq!(
+        field(&lines[0], "fingerprint"),
+        query.fingerprint.to_string()
+    );
+    assert_eq!(field(&lines[0], "args_count"), "1");
+    assert_eq!(lines[1], "record rows=3");
+    assert!(lines[2].starts_with("record duration_us="));
+    assert_eq!(lines[4], "record error=connection reset");
+    assert!(lines[5].starts_with("record duration_us="));
+}
+
+#[cfg(feature = "executor")]
+#[test]
+fn executors_record_executions() {
+    use futures_executor::block_on;
+    use sql_builder_test::{Executor, MockExecutor, Value};
+
+    let query = find(1, None);
+    let mut mock = MockExecutor::new();
+    mock.returns_affected(2)
+        .returns_rows(vec![vec![Value::I64(1)], vec![Value::I64(2)]])
+        .returns_rows(vec![vec![Value::I64(1)], vec![Value::I64(2)]])
+        .fails("connection reset");
+    let lines = capture(|| {
+        assert_eq!(block_on(mock.execute(&query)).unwrap(), 2);
+        assert_eq!(block_on(mock.fetch_all(&query)).unwrap().len(), 2);
+        assert!(block_on(mock.fetch_optional(&query)).unwrap().is_some());
+        assert!(block_on(mock.fetch_all(&query)).is_err());
+    });
+
+    let records: Vec<_> = lines
+        .iter()
+        .filter(|line| line.starts_with("record") && !line.contains("duration_us="))
+        .collect();
+    assert_eq!(
+        records,
+        [
+            "record rows=2",
+            "record rows=2",
+            "record rows=1",
+            "record error=connection reset",
+        ],
+        "{:#?}",
+        lines
+    );
+    let spans: Vec<_> = lines
+        .iter()
+        .filter(|line| line.starts_with("span query"))
+        .collect();
+    assert_eq!(spans.len(), 4);
+    assert_eq!(
+        field(spans[0], "fingerprint"),
+        query.fingerprint.to_string()
+    );
+}